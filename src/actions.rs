@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use bevy::prelude::*;
+
+use crate::gamepad::{read_gamepad, GamepadControls};
+
+// What the player wants to do, systems read Input<Action> instead of raw keys and buttons
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Fire,
+    Melee,
+    Reload,
+    NextWeapon,
+    PreviousWeapon,
+    SelectWeapon1,
+    SelectWeapon2,
+    SelectWeapon3,
+    SelectWeapon4,
+    SelectWeapon5,
+    SelectWeapon6,
+    SelectWeapon7,
+    SelectWeapon8,
+    SelectWeapon9,
+    RotateCameraLeft,
+    RotateCameraRight,
+    ZoomIn,
+    ZoomOut,
+    // Held to make mouse wheel zoom instead of switching weapons
+    ZoomModifier,
+    ToggleHealthBars,
+    Pause,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+// Every action with its bindings, saved to BINDINGS_PATH whenever they change
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings(BTreeMap<Action, Vec<Binding>>);
+
+const BINDINGS_PATH: &str = "bindings.ron";
+// Always opens pause menu whatever is saved, so player can't lock themselves out of rebinding
+pub const PAUSE_FALLBACK: KeyCode = KeyCode::F1;
+
+pub struct ActionsPlugin;
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load())
+            .init_resource::<Input<Action>>()
+            .add_systems(PreUpdate, update_actions.after(read_gamepad));
+    }
+}
+
+impl Action {
+    pub const ALL: [Action; 26] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Fire,
+        Action::Melee,
+        Action::Reload,
+        Action::NextWeapon,
+        Action::PreviousWeapon,
+        Action::SelectWeapon1,
+        Action::SelectWeapon2,
+        Action::SelectWeapon3,
+        Action::SelectWeapon4,
+        Action::SelectWeapon5,
+        Action::SelectWeapon6,
+        Action::SelectWeapon7,
+        Action::SelectWeapon8,
+        Action::SelectWeapon9,
+        Action::RotateCameraLeft,
+        Action::RotateCameraRight,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ZoomModifier,
+        Action::ToggleHealthBars,
+        Action::Pause,
+    ];
+
+    // Actions picking weapon slot directly, in slot order
+    pub const WEAPON_SLOTS: [Action; 9] = [
+        Action::SelectWeapon1,
+        Action::SelectWeapon2,
+        Action::SelectWeapon3,
+        Action::SelectWeapon4,
+        Action::SelectWeapon5,
+        Action::SelectWeapon6,
+        Action::SelectWeapon7,
+        Action::SelectWeapon8,
+        Action::SelectWeapon9,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Jump => "Jump",
+            Action::Fire => "Fire",
+            Action::Melee => "Melee",
+            Action::Reload => "Reload",
+            Action::NextWeapon => "Next weapon",
+            Action::PreviousWeapon => "Previous weapon",
+            Action::SelectWeapon1 => "Weapon 1",
+            Action::SelectWeapon2 => "Weapon 2",
+            Action::SelectWeapon3 => "Weapon 3",
+            Action::SelectWeapon4 => "Weapon 4",
+            Action::SelectWeapon5 => "Weapon 5",
+            Action::SelectWeapon6 => "Weapon 6",
+            Action::SelectWeapon7 => "Weapon 7",
+            Action::SelectWeapon8 => "Weapon 8",
+            Action::SelectWeapon9 => "Weapon 9",
+            Action::RotateCameraLeft => "Rotate camera left",
+            Action::RotateCameraRight => "Rotate camera right",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::ZoomModifier => "Zoom with mouse wheel (hold)",
+            Action::ToggleHealthBars => "Toggle health bars",
+            Action::Pause => "Pause",
+        }
+    }
+
+    fn default_bindings(&self) -> Vec<Binding> {
+        use Binding::*;
+        match self {
+            Action::MoveUp => vec![Key(KeyCode::W), Key(KeyCode::Up)],
+            Action::MoveDown => vec![Key(KeyCode::S), Key(KeyCode::Down)],
+            Action::MoveLeft => vec![Key(KeyCode::A), Key(KeyCode::Left)],
+            Action::MoveRight => vec![Key(KeyCode::D), Key(KeyCode::Right)],
+            Action::Jump => vec![Key(KeyCode::Space), Gamepad(GamepadButtonType::South)],
+            Action::Fire => vec![Mouse(MouseButton::Left), Gamepad(GamepadButtonType::RightTrigger2)],
+            Action::Melee => vec![Mouse(MouseButton::Right), Gamepad(GamepadButtonType::LeftTrigger2)],
+            Action::Reload => vec![Key(KeyCode::R), Gamepad(GamepadButtonType::West)],
+            Action::NextWeapon => vec![Gamepad(GamepadButtonType::RightTrigger)],
+            Action::PreviousWeapon => vec![Gamepad(GamepadButtonType::LeftTrigger)],
+            Action::SelectWeapon1 => vec![Key(KeyCode::Key1)],
+            Action::SelectWeapon2 => vec![Key(KeyCode::Key2)],
+            Action::SelectWeapon3 => vec![Key(KeyCode::Key3)],
+            Action::SelectWeapon4 => vec![Key(KeyCode::Key4)],
+            Action::SelectWeapon5 => vec![Key(KeyCode::Key5)],
+            Action::SelectWeapon6 => vec![Key(KeyCode::Key6)],
+            Action::SelectWeapon7 => vec![Key(KeyCode::Key7)],
+            Action::SelectWeapon8 => vec![Key(KeyCode::Key8)],
+            Action::SelectWeapon9 => vec![Key(KeyCode::Key9)],
+            Action::RotateCameraLeft => vec![Key(KeyCode::Q)],
+            Action::RotateCameraRight => vec![Key(KeyCode::E)],
+            Action::ZoomIn => vec![Key(KeyCode::Equals)],
+            Action::ZoomOut => vec![Key(KeyCode::Minus)],
+            Action::ZoomModifier => vec![Key(KeyCode::ControlLeft), Key(KeyCode::ControlRight)],
+            Action::ToggleHealthBars => vec![Key(KeyCode::H)],
+            Action::Pause => vec![Key(KeyCode::P), Gamepad(GamepadButtonType::Start)],
+        }
+    }
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+            Binding::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings(Action::ALL.iter().map(|action| (*action, action.default_bindings())).collect())
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[][..], Vec::as_slice)
+    }
+
+    // Replace binding in given slot of the action, slot past the last one adds a new binding.
+    // Refused when it's the pause fallback or would take the last binding of another action.
+    pub fn bind(&mut self, action: Action, slot: usize, binding: Binding) -> bool {
+        if binding == Binding::Key(PAUSE_FALLBACK) {
+            return false;
+        }
+        let leaves_unbound = self
+            .0
+            .iter()
+            .any(|(&other, bindings)| other != action && bindings.as_slice() == [binding]);
+        if leaves_unbound {
+            return false;
+        }
+
+        // One input triggers one action only
+        let replaced = self.get(action).get(slot).copied();
+        for bindings in self.0.values_mut() {
+            bindings.retain(|bound| *bound != binding);
+        }
+        let bindings = self.0.entry(action).or_default();
+        match replaced.and_then(|replaced| bindings.iter().position(|bound| *bound == replaced)) {
+            Some(index) => bindings[index] = binding,
+            None => bindings.push(binding),
+        }
+        true
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.0.insert(action, action.default_bindings());
+    }
+
+    // Defaults for actions the file doesn't mention, or for everything if it can't be read
+    fn load() -> Self {
+        let mut bindings = Bindings::default();
+        let Ok(text) = fs::read_to_string(BINDINGS_PATH) else {
+            return bindings;
+        };
+        match ron::from_str::<Bindings>(&text) {
+            Ok(saved) => bindings.0.extend(saved.0),
+            Err(error) => warn!("Ignoring {}: {}", BINDINGS_PATH, error),
+        }
+        // Fallback works on its own, older files may still have it bound
+        for bound in bindings.0.values_mut() {
+            bound.retain(|binding| *binding != Binding::Key(PAUSE_FALLBACK));
+        }
+        bindings
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|text| fs::write(BINDINGS_PATH, text).map_err(|error| error.to_string()));
+        if let Err(error) = result {
+            warn!("Couldn't save {}: {}", BINDINGS_PATH, error);
+        }
+    }
+}
+
+fn update_actions(
+    mut actions: ResMut<Input<Action>>,
+    bindings: Res<Bindings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepad: Res<GamepadControls>,
+) {
+    actions.clear();
+    for action in Action::ALL {
+        let (mut pressed, mut just_pressed) = (false, false);
+        for binding in bindings.get(action) {
+            let (binding_pressed, binding_just_pressed) = match *binding {
+                Binding::Key(key) => (keyboard_input.pressed(key), keyboard_input.just_pressed(key)),
+                Binding::Mouse(button) => (mouse.pressed(button), mouse.just_pressed(button)),
+                Binding::Gamepad(button) => (gamepad.buttons.pressed(button), gamepad.buttons.just_pressed(button)),
+            };
+            pressed |= binding_pressed;
+            just_pressed |= binding_just_pressed;
+        }
+        if action == Action::Pause {
+            pressed |= keyboard_input.pressed(PAUSE_FALLBACK);
+            just_pressed |= keyboard_input.just_pressed(PAUSE_FALLBACK);
+        }
+
+        // Tap shorter than a frame still counts as a press
+        if pressed || just_pressed {
+            actions.press(action);
+        }
+        if !pressed {
+            actions.release(action);
+        }
+    }
+}
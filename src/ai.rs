@@ -0,0 +1,186 @@
+use rand::Rng;
+use bevy::prelude::*;
+
+use crate::player::Player;
+use crate::navigation::NavGrid;
+use crate::perception::Perception;
+use crate::{GameState, Health};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AiState {
+    // Unaware of player
+    Idle,
+    Patrol,
+    // Engaged with player
+    Chase,
+    Strafe,
+    Retreat,
+    Attack,
+    // Overrides everything else when hurt too much
+    Flee,
+}
+
+// How an archetype behaves, brain picks states based on these
+#[derive(Clone)]
+pub struct AiProfile {
+    pub speed: f32,
+    pub attack_range: f32,
+    pub preferred_distance: f32,
+    pub distance_tolerance: f32,
+    pub flee_health: i32,
+    pub strafes: bool,
+}
+
+// What brain knows about player this frame
+pub struct Senses {
+    pub aware: bool,
+    pub distance: f32,
+    pub has_line_of_sight: bool,
+    pub health: i32,
+}
+
+#[derive(Component)]
+pub struct AiBrain {
+    pub state: AiState,
+    pub profile: AiProfile,
+    pub patrol_point: Vec3,
+    pub strafe_sign: f32,
+    state_time: f32,
+}
+
+pub struct AiPlugin;
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_brains.in_set(GameState::Playing),
+        );
+    }
+}
+
+impl AiBrain {
+    pub fn new(profile: AiProfile) -> Self {
+        AiBrain {
+            state: AiState::Idle,
+            profile,
+            patrol_point: Vec3::ZERO,
+            strafe_sign: 1.0,
+            state_time: 0.0,
+        }
+    }
+
+    pub fn can_fire(&self) -> bool {
+        matches!(self.state, AiState::Attack | AiState::Strafe)
+    }
+
+    // Transition table of the state machine
+    fn decide(&self, senses: &Senses, position: Vec3) -> AiState {
+        let profile = &self.profile;
+
+        if senses.health <= profile.flee_health && senses.aware {
+            return AiState::Flee;
+        }
+
+        if !senses.aware {
+            return match self.state {
+                AiState::Patrol => {
+                    let reached = position.distance(self.patrol_point) < 0.5;
+                    if reached || self.state_time > 6.0 {
+                        AiState::Idle
+                    } else {
+                        AiState::Patrol
+                    }
+                }
+                AiState::Idle if self.state_time < 2.0 => AiState::Idle,
+                _ => AiState::Patrol,
+            };
+        }
+
+        // Melee archetypes only need to get close
+        if profile.preferred_distance <= profile.attack_range {
+            return if senses.has_line_of_sight && senses.distance <= profile.attack_range {
+                AiState::Attack
+            } else {
+                AiState::Chase
+            };
+        }
+
+        // Shooters go find a clear shot when they can't see the player
+        if !senses.has_line_of_sight {
+            return AiState::Chase;
+        }
+        if senses.distance < profile.preferred_distance - profile.distance_tolerance {
+            return AiState::Retreat;
+        }
+        if senses.distance > profile.preferred_distance + profile.distance_tolerance {
+            return AiState::Chase;
+        }
+
+        // At preferred distance alternate between standing still and strafing
+        match self.state {
+            AiState::Attack if profile.strafes && self.state_time > 1.0 => AiState::Strafe,
+            AiState::Strafe if self.state_time < 1.5 => AiState::Strafe,
+            _ => AiState::Attack,
+        }
+    }
+
+    fn enter(&mut self, state: AiState, position: Vec3, nav_grid: &NavGrid) {
+        let mut rng = rand::thread_rng();
+        match state {
+            AiState::Patrol => {
+                // Pick random reachable point nearby
+                for _ in 0..10 {
+                    let point = position
+                        + Vec3::new(rng.gen_range(-4.0..4.0), 0.0, rng.gen_range(-4.0..4.0));
+                    if nav_grid.is_walkable(point) {
+                        self.patrol_point = point;
+                        break;
+                    }
+                }
+            }
+            AiState::Strafe => {
+                if rng.gen_bool(0.5) {
+                    self.strafe_sign = -self.strafe_sign;
+                }
+            }
+            _ => {}
+        }
+        self.state = state;
+        self.state_time = 0.0;
+    }
+}
+
+fn update_brains(
+    mut brains: Query<(&Transform, &Health, &Perception, &mut AiBrain), Without<Player>>,
+    player: Query<&Transform, With<Player>>,
+    nav_grid: Res<NavGrid>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+
+    for (transform, health, perception, mut brain) in brains.iter_mut() {
+        brain.state_time += time.delta_seconds();
+
+        // Without seeing the player, brain only knows where it was last seen
+        let known_position = if perception.can_see_player {
+            Some(player_transform.translation)
+        } else {
+            perception.last_seen_position
+        };
+        let senses = Senses {
+            aware: known_position.is_some(),
+            distance: known_position
+                .map(|position| position.distance(transform.translation))
+                .unwrap_or(f32::INFINITY),
+            has_line_of_sight: perception.can_see_player,
+            health: health.0,
+        };
+
+        let next_state = brain.decide(&senses, transform.translation);
+        if next_state != brain.state {
+            brain.enter(next_state, transform.translation, &nav_grid);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::time::Duration;
+
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::combat::{apply_damage, despawn_dead, DamageEvent, DeathEvent};
+use crate::GameState;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AnimationKind {
+    Idle,
+    Run,
+    Attack,
+    Hit,
+    Death,
+}
+
+const ANIMATION_KINDS: [AnimationKind; 5] = [
+    AnimationKind::Idle,
+    AnimationKind::Run,
+    AnimationKind::Attack,
+    AnimationKind::Hit,
+    AnimationKind::Death,
+];
+
+// Limb nodes shared by all character models
+const LIMBS: [&str; 6] = ["armLeft", "armRight", "body", "head", "legLeft", "legRight"];
+
+const TRANSITION: Duration = Duration::from_millis(150);
+// Horizontal speed above which characters play run clip
+const RUN_SPEED: f32 = 0.5;
+
+struct OneShot {
+    kind: AnimationKind,
+    duration: f32,
+    elapsed: f32,
+}
+
+// Picks clip for character from its movement and combat events
+#[derive(Component, Default)]
+pub struct AnimationController {
+    // Root node of the model, the limbs are its children
+    rig: Option<Entity>,
+    rig_name: String,
+    // Child of the character holding whole model
+    model: Option<Entity>,
+    // File the model came from, its named clips replace built ones
+    gltf: Option<Handle<Gltf>>,
+    current: Option<AnimationKind>,
+    one_shot: Option<OneShot>,
+    restart: bool,
+}
+
+// Model left behind to play death clip after its character got despawned
+#[derive(Component)]
+struct Corpse(Timer);
+
+// Clips for every rig, keyed by the name of its root node
+#[derive(Resource, Default)]
+struct AnimationLibrary {
+    rigs: HashMap<String, HashMap<AnimationKind, Handle<AnimationClip>>>,
+}
+
+pub struct CharacterAnimationPlugin;
+impl Plugin for CharacterAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AnimationLibrary>()
+            .add_systems(
+                Update,
+                (
+                    attach_rigs,
+                    react_to_damage,
+                    update_animations,
+                    leave_corpses.after(apply_damage).before(despawn_dead),
+                    despawn_corpses,
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+impl AnimationKind {
+    // Length of the clip when played at normal speed
+    fn duration(&self) -> f32 {
+        match self {
+            AnimationKind::Idle => 2.0,
+            AnimationKind::Run => 0.6,
+            AnimationKind::Attack => 0.35,
+            AnimationKind::Hit => 0.3,
+            AnimationKind::Death => 0.8,
+        }
+    }
+
+    // Name of the clip exported from Blender with the model
+    fn clip_name(&self) -> &'static str {
+        match self {
+            AnimationKind::Idle => "idle",
+            AnimationKind::Run => "run",
+            AnimationKind::Attack => "attack",
+            AnimationKind::Hit => "hit",
+            AnimationKind::Death => "death",
+        }
+    }
+
+    fn repeats(&self) -> bool {
+        matches!(self, AnimationKind::Idle | AnimationKind::Run)
+    }
+
+    // Rotation of limb relative to its rest pose at given progress of the clip
+    fn pose(&self, limb: &str, progress: f32) -> Quat {
+        let wave = (progress * TAU).sin();
+        let swing = (progress * PI).sin();
+        match (self, limb) {
+            (AnimationKind::Idle, "head") => Quat::from_rotation_x(0.05 * wave),
+            (AnimationKind::Idle, "armLeft") => Quat::from_rotation_z(0.05 * wave),
+            (AnimationKind::Idle, "armRight") => Quat::from_rotation_z(-0.05 * wave),
+            (AnimationKind::Run, "legLeft") => Quat::from_rotation_x(0.6 * wave),
+            (AnimationKind::Run, "legRight") => Quat::from_rotation_x(-0.6 * wave),
+            (AnimationKind::Run, "armLeft") => Quat::from_rotation_x(-0.5 * wave),
+            (AnimationKind::Run, "armRight") => Quat::from_rotation_x(0.5 * wave),
+            (AnimationKind::Attack, "armRight") => Quat::from_rotation_x(-1.4 * swing),
+            (AnimationKind::Attack, "body") => Quat::from_rotation_y(0.3 * swing),
+            (AnimationKind::Hit, "body") => Quat::from_rotation_x(0.3 * swing),
+            (AnimationKind::Hit, "head") => Quat::from_rotation_x(0.4 * swing),
+            (AnimationKind::Death, "armLeft") => Quat::from_rotation_z(0.8 * progress),
+            (AnimationKind::Death, "armRight") => Quat::from_rotation_z(-0.8 * progress),
+            _ => Quat::IDENTITY,
+        }
+    }
+}
+
+impl AnimationController {
+    pub fn new() -> Self {
+        AnimationController::default()
+    }
+
+    // Play clip once over locomotion, stretched to given duration
+    pub fn play_once(&mut self, kind: AnimationKind, duration: f32) {
+        self.one_shot = Some(OneShot {
+            kind,
+            duration,
+            elapsed: 0.0,
+        });
+        self.restart = true;
+    }
+
+    pub fn play(&mut self, kind: AnimationKind) {
+        self.play_once(kind, kind.duration());
+    }
+
+    // Clip exported with the model if it has one, otherwise the one built from rest pose
+    fn clip(&self, kind: AnimationKind, library: &AnimationLibrary, gltfs: &Assets<Gltf>) -> Option<Handle<AnimationClip>> {
+        self.gltf
+            .as_ref()
+            .and_then(|gltf| gltfs.get(gltf))
+            .and_then(|gltf| gltf.named_animations.get(kind.clip_name()))
+            .or_else(|| library.rigs.get(&self.rig_name)?.get(&kind))
+            .map(Handle::clone_weak)
+    }
+}
+
+fn rotation_curve(duration: f32, rest: Quat, pose: impl Fn(f32) -> Quat) -> VariableCurve {
+    const SAMPLES: usize = 12;
+    let keyframe_timestamps: Vec<f32> = (0..=SAMPLES)
+        .map(|sample| duration * sample as f32 / SAMPLES as f32)
+        .collect();
+    let keyframes = keyframe_timestamps
+        .iter()
+        .map(|time| rest * pose(time / duration))
+        .collect();
+    VariableCurve {
+        keyframe_timestamps,
+        keyframes: Keyframes::Rotation(keyframes),
+    }
+}
+
+// Models currently ship without clips, so every clip is built around rest pose of the rig
+fn build_clip(kind: AnimationKind, rig_name: &Name, rig_rest: Quat, limb_rests: &[(Name, Quat)]) -> AnimationClip {
+    let mut clip = AnimationClip::default();
+    for (limb, rest) in limb_rests.iter() {
+        clip.add_curve_to_path(
+            EntityPath {
+                parts: vec![rig_name.clone(), limb.clone()],
+            },
+            rotation_curve(kind.duration(), *rest, |progress| kind.pose(limb.as_str(), progress)),
+        );
+    }
+
+    // Whole model topples over when dying
+    clip.add_curve_to_path(
+        EntityPath {
+            parts: vec![rig_name.clone()],
+        },
+        rotation_curve(kind.duration(), rig_rest, |progress| match kind {
+            AnimationKind::Death => Quat::from_rotation_x(-FRAC_PI_2 * progress * progress),
+            _ => Quat::IDENTITY,
+        }),
+    );
+    clip
+}
+
+// Find rig in the character scene once it is spawned and give it an animation player
+fn attach_rigs(
+    mut controllers: Query<(Entity, &mut AnimationController)>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    names: Query<&Name>,
+    transforms: Query<&Transform>,
+    animation_players: Query<(), With<AnimationPlayer>>,
+    scenes: Query<&Handle<Scene>>,
+    mut library: ResMut<AnimationLibrary>,
+    mut clips: ResMut<Assets<AnimationClip>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for (entity, mut controller) in controllers.iter_mut() {
+        if controller.rig.is_some() {
+            continue;
+        }
+
+        let Some(arm) = children
+            .iter_descendants(entity)
+            .find(|&node| names.get(node).map_or(false, |name| name.as_str() == "armRight"))
+        else {
+            continue;
+        };
+        let Ok(rig) = parents.get(arm).map(|parent| parent.get()) else {
+            continue;
+        };
+        let Ok(rig_name) = names.get(rig) else {
+            continue;
+        };
+
+        if !library.rigs.contains_key(rig_name.as_str()) {
+            let limb_rests: Vec<(Name, Quat)> = children
+                .iter_descendants(rig)
+                .filter_map(|node| {
+                    let name = names.get(node).ok()?;
+                    let transform = transforms.get(node).ok()?;
+                    LIMBS.contains(&name.as_str()).then(|| (name.clone(), transform.rotation))
+                })
+                .collect();
+            let rig_rest = transforms.get(rig).map_or(Quat::IDENTITY, |transform| transform.rotation);
+            let rig_clips = ANIMATION_KINDS
+                .iter()
+                .map(|&kind| (kind, clips.add(build_clip(kind, rig_name, rig_rest, &limb_rests))))
+                .collect();
+            library.rigs.insert(rig_name.to_string(), rig_clips);
+        }
+
+        // Reuse player from the scene if model comes with one
+        if !animation_players.contains(rig) {
+            commands.entity(rig).insert(AnimationPlayer::default());
+        }
+        controller.rig = Some(rig);
+        controller.rig_name = rig_name.to_string();
+        controller.model = parents
+            .iter_ancestors(rig)
+            .take_while(|&ancestor| ancestor != entity)
+            .last()
+            .or(Some(rig));
+        controller.gltf = controller
+            .model
+            .and_then(|model| scenes.get(model).ok())
+            .and_then(|scene| asset_server.get_handle_path(scene))
+            .map(|path| asset_server.load(path.path().to_path_buf()));
+    }
+}
+
+fn react_to_damage(
+    mut controllers: Query<&mut AnimationController>,
+    mut damage_event_reader: EventReader<DamageEvent>,
+) {
+    for event in damage_event_reader.iter() {
+        if let Ok(mut controller) = controllers.get_mut(event.target) {
+            controller.play(AnimationKind::Hit);
+        }
+    }
+}
+
+fn update_animations(
+    mut controllers: Query<(&mut AnimationController, Option<&Velocity>)>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    library: Res<AnimationLibrary>,
+    gltfs: Res<Assets<Gltf>>,
+    clips: Res<Assets<AnimationClip>>,
+    time: Res<Time>,
+) {
+    for (mut controller, velocity) in controllers.iter_mut() {
+        let Some(rig) = controller.rig else {
+            continue;
+        };
+        let Ok(mut animation_player) = animation_players.get_mut(rig) else {
+            continue;
+        };
+
+        // One-shot clips play over locomotion until they finish
+        let finished = controller.one_shot.as_mut().map_or(false, |one_shot| {
+            one_shot.elapsed += time.delta_seconds();
+            one_shot.elapsed >= one_shot.duration
+        });
+        if finished {
+            controller.one_shot = None;
+        }
+        let (kind, duration) = match controller.one_shot.as_ref() {
+            Some(one_shot) => (one_shot.kind, Some(one_shot.duration)),
+            None => {
+                let horizontal_speed = velocity.map_or(0.0, |velocity| Vec2::new(velocity.linvel.x, velocity.linvel.z).length());
+                if horizontal_speed > RUN_SPEED {
+                    (AnimationKind::Run, None)
+                } else {
+                    (AnimationKind::Idle, None)
+                }
+            }
+        };
+
+        let restart = std::mem::take(&mut controller.restart);
+        if controller.current == Some(kind) && !restart {
+            continue;
+        }
+        let Some(handle) = controller.clip(kind, &library, &gltfs) else {
+            continue;
+        };
+        // One-shots are stretched to their duration, exported clips can be of any length
+        let speed = duration.map_or(1.0, |duration| {
+            clips.get(&handle).map_or(kind.duration(), AnimationClip::duration) / duration
+        });
+        if kind.repeats() {
+            animation_player.play_with_transition(handle, TRANSITION).repeat();
+        } else {
+            animation_player.start_with_transition(handle, TRANSITION);
+        }
+        animation_player.set_speed(speed);
+        controller.current = Some(kind);
+    }
+}
+
+// Detach model of dead character so death clip can play after it is despawned
+fn leave_corpses(
+    controllers: Query<&AnimationController>,
+    mut animation_players: Query<&mut AnimationPlayer>,
+    library: Res<AnimationLibrary>,
+    gltfs: Res<Assets<Gltf>>,
+    mut death_event_reader: EventReader<DeathEvent>,
+    mut commands: Commands,
+) {
+    for event in death_event_reader.iter() {
+        let Ok(controller) = controllers.get(event.entity) else {
+            continue;
+        };
+        let (Some(rig), Some(model)) = (controller.rig, controller.model) else {
+            continue;
+        };
+        let Some(death_clip) = controller.clip(AnimationKind::Death, &library, &gltfs) else {
+            continue;
+        };
+
+        commands
+            .entity(model)
+            .remove_parent_in_place()
+            .insert(Corpse(Timer::from_seconds(1.5, TimerMode::Once)));
+        if let Ok(mut animation_player) = animation_players.get_mut(rig) {
+            animation_player
+                .start_with_transition(death_clip, TRANSITION)
+                .set_speed(1.0);
+        }
+    }
+}
+
+fn despawn_corpses(
+    mut corpses: Query<(Entity, &mut Corpse)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut corpse) in corpses.iter_mut() {
+        if corpse.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
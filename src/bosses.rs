@@ -7,7 +7,13 @@ use bevy_rapier3d::prelude::*;
 use bevy::render::mesh::shape as render_shape;
 
 use crate::player::Player;
-use crate::{GameState, Health, FloatingTextEvent};
+use crate::navigation::PathFollower;
+use crate::steering::Steering;
+use crate::perception::Perception;
+use crate::{GameState, Health};
+use crate::combat::{Armor, DamageEvent, DamageType, ScoreValue};
+use crate::animation::{AnimationController, AnimationKind};
+use crate::status::{StatusEffect, StatusEffects, StatusEvent, StatusKind};
 
 #[derive(Component)]
 struct BossBullet{
@@ -36,23 +42,18 @@ pub enum BossType {
 #[derive(Component)]
 pub struct Boss {
     boss_type: BossType,
-    boss_state: BossState
+    boss_state: BossState,
+    // Own cooldown, so slowing one boss doesn't slow the others
+    attack_timer: Timer,
 }
 
 #[derive(Resource)]
 struct BossSpawnTimer(Timer);
 
-#[derive(Resource)]
-struct BossAttackTimer(Timer);
-
 pub struct BossesPlugin;
 impl Plugin for BossesPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(BossAttackTimer(Timer::from_seconds(
-            2.0,
-            TimerMode::Repeating,
-        )))
-        .insert_resource(BossSpawnTimer(Timer::from_seconds(
+        app.insert_resource(BossSpawnTimer(Timer::from_seconds(
             20.0,
             TimerMode::Repeating,
         )))
@@ -105,8 +106,13 @@ fn spawn_boss(
     let x = angle.sin() * 7.0;
     let z = angle.cos() * 7.0;
     commands
-        .spawn(Boss{boss_state: BossState::Attacking, boss_type: boss_type})
-        .insert(Health(10))
+        .spawn(Boss{
+            boss_state: BossState::Attacking,
+            boss_type: boss_type,
+            attack_timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+        })
+        .insert(Health(100))
+        .insert(ScoreValue(50))
         .insert(PbrBundle {
             transform: Transform::from_xyz(x, 1.0, z),
             ..default()
@@ -124,7 +130,21 @@ fn spawn_boss(
         })
         .insert(RigidBody::Dynamic)
         .insert(Velocity::zero())
-        .insert(Collider::capsule_y(1.0, 1.0));
+        .insert(Collider::capsule_y(1.0, 1.0))
+        .insert(PathFollower::new(0.5))
+        .insert(Steering {
+            separation_radius: 3.0,
+            separation_weight: 0.8,
+            avoidance_distance: 2.5,
+            avoidance_weight: 2.0,
+            surround_radius: 0.0,
+            flank_angle: 0.0,
+        })
+        .insert(Perception::new(30.0, PI, 5.0))
+        .insert(AnimationController::new())
+        // Bosses can be slowed down but never locked in place
+        .insert(StatusEffects::new(&[StatusKind::Freeze, StatusKind::Stun]))
+        .insert(Armor::new(5, &[(DamageType::Explosive, 0.25), (DamageType::Fire, 0.25)]));
 }
 
 fn rotate_bosses(
@@ -143,17 +163,26 @@ fn rotate_bosses(
     }
 }
 
-fn move_bosses(
-    mut bosses: Query<(&Transform, &mut Velocity), (With<Boss>, Without<Player>)>,
+pub fn move_bosses(
+    mut bosses: Query<
+        (Entity, &Transform, &mut Velocity, &mut PathFollower, &Steering, &StatusEffects),
+        (With<Boss>, Without<Player>),
+    >,
+    neighbours: Query<(Entity, &Transform), (With<Steering>, Without<Player>)>,
     mut player_transform: Query<&Transform, (With<Player>, Without<Boss>)>,
+    rapier_context: Res<RapierContext>,
     time: Res<Time>,
 ) {
     const SPEED: f32 = 100.0;
-    for (boss_transform, mut boss_velocity) in bosses.iter_mut() {
-        // Get vector representing direction from enemy to player
-        let mut direction_vec =
-            player_transform.single_mut().translation - boss_transform.translation;
-        direction_vec = direction_vec.normalize();
+    let neighbours: Vec<(Entity, Vec3)> = neighbours
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation))
+        .collect();
+
+    for (boss_entity, boss_transform, mut boss_velocity, mut path_follower, steering, status_effects) in bosses.iter_mut() {
+        // Get direction along path leading around obstacles to player
+        path_follower.target = player_transform.single_mut().translation;
+        let path_direction = path_follower.direction(boss_transform.translation);
 
         // Calculate distance of vectors, so enemy chases player only until it's near him
         let vec2_player = Vec2::new(
@@ -165,21 +194,23 @@ fn move_bosses(
             boss_transform.translation[2],
         );
         if vec2_player.distance(vec2_enemy) > 2.0 {
-            boss_velocity.linvel[0] = direction_vec[0] * SPEED * time.delta_seconds();
-            boss_velocity.linvel[2] = direction_vec[2] * SPEED * time.delta_seconds();
+            let direction_vec = steering.steer(boss_entity, boss_transform.translation, path_direction, &neighbours, &rapier_context);
+            let speed = SPEED * status_effects.speed_multiplier();
+            boss_velocity.linvel[0] = direction_vec[0] * speed * time.delta_seconds();
+            boss_velocity.linvel[2] = direction_vec[2] * speed * time.delta_seconds();
         }
     }
 }
 
 fn boss_melee_attack(
-    mut enemies: Query<(&Transform, &mut Boss), With<Boss>>,
-    mut player: Query<(Entity, &mut Health, &Transform), With<Player>>,
+    mut enemies: Query<(Entity, &Transform, &mut Boss, &mut AnimationController, &StatusEffects), With<Boss>>,
+    player: Query<Entity, With<Player>>,
     rapier_context: Res<RapierContext>,
     time: Res<Time>,
-    mut timer: ResMut<BossAttackTimer>,
-    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut status_event_writer: EventWriter<StatusEvent>,
 ) {
-    for (boss_transform, mut boss) in enemies.iter_mut() {
+    for (boss_entity, boss_transform, mut boss, mut animation, status_effects) in enemies.iter_mut() {
         let shape = Collider::ball(2.0);
         let shape_pos = boss_transform.translation;
         let shape_rot = boss_transform.rotation;
@@ -187,7 +218,7 @@ fn boss_melee_attack(
 
         let mut player_is_hit = false;
         rapier_context.intersections_with_shape(shape_pos, shape_rot, &shape, filter, |entity| {
-            if entity == player.single().0 {
+            if entity == player.single() {
                 player_is_hit = true;
             }
             true
@@ -197,20 +228,31 @@ fn boss_melee_attack(
             match boss.boss_state {
                 BossState::Attacking => {
                     // Attack player
-                    player.single_mut().1.0 -= 1;
-
-                    // Create floating text
-                    floating_text_event_writer.send(FloatingTextEvent {
-                        translation: player.single_mut().2.translation,
-                        text: "-1".into(),
-                        color: Color::rgb(0.7, 0.0, 0.0),
+                    damage_event_writer.send(DamageEvent {
+                        target: player.single(),
+                        source: Some(boss_entity),
+                        amount: 10,
+                        damage_type: DamageType::Kinetic,
+                        crit_chance: 0.0,
+                        over_time: false,
+                    });
+                    // Boss hits hard enough to daze the player
+                    status_event_writer.send(StatusEvent {
+                        target: player.single(),
+                        source: Some(boss_entity),
+                        effect: StatusEffect {
+                            kind: StatusKind::Stun,
+                            duration: 0.6,
+                        },
                     });
 
+                    animation.play(AnimationKind::Attack);
                     boss.boss_state = BossState::Cooldown;
                 }
                 _ => {
-                    // Boss attack cooldown
-                    if !timer.0.tick(time.delta()).finished() {
+                    // Boss attack cooldown, slowed bosses attack less often
+                    let delta = time.delta().mul_f32(status_effects.attack_speed_multiplier());
+                    if !boss.attack_timer.tick(delta).finished() {
                         continue;
                     }
                     boss.boss_state = BossState::Attacking;
@@ -223,22 +265,26 @@ fn boss_melee_attack(
 fn boss_shoot_attack(
     mut player: Query<(&Transform, &mut Health), (With<Player>, Without<Boss>)>,
     mut bosses: Query<
-        (Entity, &Transform, &mut Boss),
+        (Entity, &Transform, &mut Boss, &Perception, &mut AnimationController, &StatusEffects),
         (With<Boss>, Without<Player>),
     >,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     time: Res<Time>,
-    mut timer: ResMut<BossAttackTimer>,
 ) {
-    for (boss_entity, boss_transform, mut boss) in bosses.iter_mut() {
+    for (boss_entity, boss_transform, mut boss, perception, mut animation, status_effects) in bosses.iter_mut() {
 
         let direction =
             (player.single_mut().0.translation - boss_transform.translation).normalize();
 
         match boss.boss_state {
             BossState::Attacking => {
+                // Keep chasing until there is clear line to player
+                if !perception.can_see_player {
+                    continue;
+                }
+
                 match boss.boss_type {
                     BossType::Boss1 => {
                         boss1_attack(boss_entity, *boss_transform, direction, &mut meshes, &mut materials, &mut commands);
@@ -248,11 +294,13 @@ fn boss_shoot_attack(
                     }
                 }
 
+                animation.play(AnimationKind::Attack);
                 boss.boss_state = BossState::Cooldown;
             }
             _ => {
                 // Enemy attack cooldown
-                if !timer.0.tick(time.delta()).finished() {
+                let delta = time.delta().mul_f32(status_effects.attack_speed_multiplier());
+                if !boss.attack_timer.tick(delta).finished() {
                     continue;
                 }
                 boss.boss_state = BossState::Attacking;
@@ -328,10 +376,11 @@ fn move_boss_bullets(
         (Entity, &mut Velocity, &BossBullet, &Transform),
         With<BossBullet>,
     >,
-    mut player: Query<(Entity, &mut Health), With<Player>>,
+    player: Query<Entity, With<Player>>,
     rapier_context: Res<RapierContext>,
     mut commands: Commands,
     time: Res<Time>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
 ) {
     const SPEED: f32 = 600.0;
     for (bullet_entity, mut vel, bullet_struct, transform) in bullets.iter_mut() {
@@ -359,8 +408,15 @@ fn move_boss_bullets(
             // Despawn bullet after it hits anything
             commands.entity(bullet_entity).despawn_recursive();
 
-            if entity == player.single().0 {
-                player.single_mut().1 .0 -= 1;
+            if entity == player.single() {
+                damage_event_writer.send(DamageEvent {
+                    target: entity,
+                    source: Some(bullet_struct.shooter),
+                    amount: 10,
+                    damage_type: DamageType::Kinetic,
+                    crit_chance: 0.0,
+                    over_time: false,
+                });
             }
         }
     }
@@ -398,10 +454,11 @@ fn move_boss_missiles(
         (Entity, &mut Velocity, &BossMissile, &Transform),
         With<BossMissile>,
     >,
-    mut player: Query<(Entity, &mut Health, &Transform), With<Player>>,
+    mut player: Query<(Entity, &Transform), With<Player>>,
     rapier_context: Res<RapierContext>,
     mut commands: Commands,
     time: Res<Time>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
 ) {
     const SPEED: f32 = 200.0;
     for (missile_entity, mut missile_vel, missile_struct, missile_transform) in missiles.iter_mut() {
@@ -411,7 +468,7 @@ fn move_boss_missiles(
         }
 
         // Get vector representing direction from enemy to player
-        let mut direction_vec = player.single_mut().2.translation - missile_transform.translation;
+        let mut direction_vec = player.single_mut().1.translation - missile_transform.translation;
         direction_vec = direction_vec.normalize();
 
         missile_vel.linvel[0] = direction_vec[0] * SPEED * time.delta_seconds();
@@ -434,7 +491,14 @@ fn move_boss_missiles(
             commands.entity(missile_entity).despawn_recursive();
 
             if entity == player.single().0 {
-                player.single_mut().1.0 -= 1;
+                damage_event_writer.send(DamageEvent {
+                    target: entity,
+                    source: Some(missile_struct.shooter),
+                    amount: 10,
+                    damage_type: DamageType::Explosive,
+                    crit_chance: 0.0,
+                    over_time: false,
+                });
             }
         }
     }
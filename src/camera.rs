@@ -0,0 +1,131 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use crate::player::Player;
+use crate::actions::Action;
+use crate::{Cursor, GameState, MainCamera};
+
+// Orbits the player at fixed pitch, leaning a bit towards the cursor
+#[derive(Component)]
+pub struct CameraRig {
+    pub pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    // Radians per second while rotation key is held
+    pub rotation_speed: f32,
+    // Distance change per zoom step
+    pub zoom_step: f32,
+    // How quickly camera catches up with its target, higher is snappier
+    pub smoothing: f32,
+    // Fraction of the way to the cursor camera looks ahead
+    pub look_ahead: f32,
+    pub max_look_ahead: f32,
+    yaw: f32,
+    target_yaw: f32,
+    distance: f32,
+    target_distance: f32,
+    focus: Vec3,
+}
+
+pub struct CameraRigPlugin;
+impl Plugin for CameraRigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (control_camera, move_camera.after(control_camera)).in_set(GameState::Playing),
+        );
+    }
+}
+
+impl CameraRig {
+    // Rig keeping camera at given offset from what it looks at, yaw 0 looks along +X
+    pub fn new(offset: Vec3, focus: Vec3) -> Self {
+        let horizontal = Vec2::new(offset.x, offset.z).length();
+        let distance = offset.length();
+        CameraRig {
+            pitch: offset.y.atan2(horizontal),
+            min_distance: distance * 0.5,
+            max_distance: distance * 1.6,
+            rotation_speed: 2.0,
+            zoom_step: 1.0,
+            smoothing: 10.0,
+            look_ahead: 0.25,
+            max_look_ahead: 3.0,
+            yaw: 0.0,
+            target_yaw: 0.0,
+            distance,
+            target_distance: distance,
+            focus,
+        }
+    }
+}
+
+// Rotate around the player with actions, zoom with actions or zoom modifier and mouse wheel
+// Camera forward and right flattened onto the ground, so up on input or radar is up on the screen
+pub fn camera_basis(camera_transform: &Transform) -> (Vec3, Vec3) {
+    let forward = camera_transform.forward();
+    let right = camera_transform.right();
+    (
+        Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero(),
+        Vec3::new(right.x, 0.0, right.z).normalize_or_zero(),
+    )
+}
+
+fn control_camera(
+    mut camera: Query<&mut CameraRig>,
+    actions: Res<Input<Action>>,
+    mut mouse_wheel_er: EventReader<MouseWheel>,
+    time: Res<Time>,
+) {
+    const PIXELS_PER_STEP: f32 = 50.0;
+    let mut rig = camera.single_mut();
+
+    if actions.pressed(Action::RotateCameraLeft) {
+        rig.target_yaw += rig.rotation_speed * time.delta_seconds();
+    }
+    if actions.pressed(Action::RotateCameraRight) {
+        rig.target_yaw -= rig.rotation_speed * time.delta_seconds();
+    }
+
+    // Plain wheel is left for weapon switching
+    let mut steps = 0.0;
+    let zooming_with_wheel = actions.pressed(Action::ZoomModifier);
+    for event in mouse_wheel_er.iter() {
+        if zooming_with_wheel {
+            steps += match event.unit {
+                MouseScrollUnit::Line => event.y,
+                MouseScrollUnit::Pixel => event.y / PIXELS_PER_STEP,
+            };
+        }
+    }
+    if actions.just_pressed(Action::ZoomIn) {
+        steps += 1.0;
+    }
+    if actions.just_pressed(Action::ZoomOut) {
+        steps -= 1.0;
+    }
+    rig.target_distance = (rig.target_distance - steps * rig.zoom_step).clamp(rig.min_distance, rig.max_distance);
+}
+
+pub fn move_camera(
+    mut camera: Query<(&mut Transform, &mut CameraRig), (With<MainCamera>, Without<Player>)>,
+    player: Query<&Transform, (With<Player>, Without<MainCamera>)>,
+    cursor: Query<&Transform, (With<Cursor>, Without<Player>, Without<MainCamera>)>,
+    time: Res<Time>,
+) {
+    let (mut camera_transform, mut rig) = camera.single_mut();
+    let player_position = player.single().translation;
+
+    // Look ahead towards the cursor, but never so far that player leaves the screen
+    let to_cursor = cursor.get_single().map_or(Vec3::ZERO, |cursor| cursor.translation - player_position);
+    let look_ahead = (Vec3::new(to_cursor.x, 0.0, to_cursor.z) * rig.look_ahead).clamp_length_max(rig.max_look_ahead);
+
+    // Exponential smoothing behaves the same at any frame rate
+    let blend = 1.0 - (-rig.smoothing * time.delta_seconds()).exp();
+    rig.yaw += (rig.target_yaw - rig.yaw) * blend;
+    rig.distance += (rig.target_distance - rig.distance) * blend;
+    rig.focus = rig.focus.lerp(player_position + look_ahead, blend);
+
+    let offset = Quat::from_rotation_y(rig.yaw) * Vec3::new(-rig.pitch.cos(), rig.pitch.sin(), 0.0) * rig.distance;
+    *camera_transform = Transform::from_translation(rig.focus + offset).looking_at(rig.focus, Vec3::Y);
+}
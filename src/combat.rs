@@ -0,0 +1,227 @@
+use rand::Rng;
+use serde::Deserialize;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::Player;
+use crate::enemies::move_enemies;
+use crate::bosses::move_bosses;
+use crate::powerups::{DoubleDamage, PowerUp};
+use crate::floating_text::DamageNumberEvent;
+use crate::{Game, GameState, Health, FloatingTextEvent};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DamageType {
+    #[default]
+    Kinetic,
+    Explosive,
+    Fire,
+    // Goes straight through armor
+    Energy,
+}
+
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub amount: i32,
+    pub damage_type: DamageType,
+    // Chance that hit does critical damage, rolled when it lands
+    pub crit_chance: f32,
+    // Tick of burn, poison and alike rather than a hit
+    pub over_time: bool,
+}
+
+#[derive(Event)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub translation: Vec3,
+}
+
+// Absorbs whole hits before health is touched
+#[derive(Component)]
+pub struct Shield(pub i32);
+
+// Flat reduction of every hit and resistances per damage type
+#[derive(Component, Default)]
+pub struct Armor {
+    pub flat: i32,
+    // Fraction of damage ignored, negative values are weaknesses
+    pub resistances: Vec<(DamageType, f32)>,
+}
+
+// Score player gets for killing entity
+#[derive(Component)]
+pub struct ScoreValue(pub u32);
+
+// Push added on top of entity's own movement until it wears off
+#[derive(Component)]
+pub struct Knockback {
+    velocity: Vec3,
+}
+
+pub struct CombatPlugin;
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<DeathEvent>()
+            .add_systems(
+                Update,
+                (
+                    apply_damage,
+                    despawn_dead.after(apply_damage),
+                    // Movement systems overwrite velocity, so push comes after them
+                    apply_knockback.after(move_enemies).after(move_bosses),
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+const CRIT_MULTIPLIER: f32 = 2.0;
+
+impl DamageType {
+    pub fn color(&self) -> Color {
+        match self {
+            DamageType::Kinetic => Color::rgb(0.7, 0.0, 0.0),
+            DamageType::Explosive => Color::GOLD,
+            DamageType::Fire => Color::ORANGE_RED,
+            DamageType::Energy => Color::CYAN,
+        }
+    }
+}
+
+impl Armor {
+    pub fn new(flat: i32, resistances: &[(DamageType, f32)]) -> Self {
+        Armor {
+            flat,
+            resistances: resistances.to_vec(),
+        }
+    }
+
+    // Stacks with resistance entity already has against that type
+    pub fn add_resistance(&mut self, damage_type: DamageType, amount: f32) {
+        match self.resistances.iter_mut().find(|resistance| resistance.0 == damage_type) {
+            Some(resistance) => resistance.1 += amount,
+            None => self.resistances.push((damage_type, amount)),
+        }
+    }
+
+    fn resistance(&self, damage_type: DamageType) -> f32 {
+        self.resistances
+            .iter()
+            .find(|resistance| resistance.0 == damage_type)
+            .map_or(0.0, |resistance| resistance.1)
+    }
+
+    // Every hit that gets through does at least 1 damage
+    fn mitigate(&self, amount: i32, damage_type: DamageType) -> i32 {
+        let resisted = (amount as f32 * (1.0 - self.resistance(damage_type))).round() as i32;
+        let armored = match damage_type {
+            DamageType::Energy => resisted,
+            _ => resisted - self.flat,
+        };
+        armored.max(1)
+    }
+}
+
+impl Knockback {
+    pub fn new(velocity: Vec3) -> Self {
+        Knockback { velocity }
+    }
+}
+
+pub fn apply_damage(
+    mut targets: Query<(&mut Health, &Transform, Option<&mut Shield>, Option<&Armor>, Option<&ScoreValue>, Option<&Player>)>,
+    double_damage: Query<(), With<PowerUp<DoubleDamage>>>,
+    mut damage_event_reader: EventReader<DamageEvent>,
+    mut death_event_writer: EventWriter<DeathEvent>,
+    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
+    mut damage_number_event_writer: EventWriter<DamageNumberEvent>,
+    mut game: ResMut<Game>,
+) {
+    for event in damage_event_reader.iter() {
+        let Ok((mut health, transform, shield, armor, score_value, player)) = targets.get_mut(event.target) else {
+            continue;
+        };
+
+        // Enemy could be already dead from another hit in this frame, player keeps taking hits until game over
+        if health.0 <= 0 && player.is_none() {
+            continue;
+        }
+
+        if let Some(mut shield) = shield {
+            if shield.0 > 0 {
+                shield.0 -= 1;
+                floating_text_event_writer.send(FloatingTextEvent {
+                    translation: transform.translation,
+                    text: "Blocked".into(),
+                    color: Color::rgb(0.3, 0.6, 1.0),
+                    size: 40.0,
+                });
+                continue;
+            }
+        }
+
+        let mut amount = match event.source {
+            Some(source) if double_damage.contains(source) => event.amount * 2,
+            _ => event.amount,
+        };
+        let critical = event.crit_chance > 0.0 && rand::thread_rng().gen::<f32>() < event.crit_chance;
+        if critical {
+            amount = (amount as f32 * CRIT_MULTIPLIER).round() as i32;
+        }
+        if let Some(armor) = armor {
+            amount = armor.mitigate(amount, event.damage_type);
+        }
+        health.0 -= amount;
+
+        damage_number_event_writer.send(DamageNumberEvent {
+            target: event.target,
+            source: event.source,
+            translation: transform.translation,
+            amount,
+            color: event.damage_type.color(),
+            critical,
+            over_time: event.over_time,
+        });
+
+        // Only enemies and bosses get despawned when killed
+        if health.0 <= 0 && player.is_none() {
+            if let Some(score_value) = score_value {
+                game.score += score_value.0;
+            }
+            death_event_writer.send(DeathEvent {
+                entity: event.target,
+                translation: transform.translation,
+            });
+        }
+    }
+}
+
+// Despawn separately so death reactions can still look at the entity
+pub fn despawn_dead(
+    mut death_event_reader: EventReader<DeathEvent>,
+    mut commands: Commands,
+) {
+    for event in death_event_reader.iter() {
+        commands.entity(event.entity).despawn_recursive();
+    }
+}
+
+fn apply_knockback(
+    mut knocked: Query<(Entity, &mut Velocity, &mut Knockback)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    const DECAY: f32 = 8.0;
+    for (entity, mut velocity, mut knockback) in knocked.iter_mut() {
+        velocity.linvel.x += knockback.velocity.x;
+        velocity.linvel.z += knockback.velocity.z;
+
+        knockback.velocity *= (1.0 - DECAY * time.delta_seconds()).max(0.0);
+        if knockback.velocity.length() < 0.1 {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::Player;
+use crate::ai::AiBrain;
+use crate::enemies::{spawn_enemy, Enemy};
+use crate::feedback::ExplosionEvent;
+use crate::combat::{apply_damage, Armor, DamageEvent, DamageType, DeathEvent, ScoreValue, Shield};
+use crate::floating_text::DamageNumberEvent;
+use crate::{Difficulty, FloatingTextEvent, GameState, Health, MaxHealth};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Affix {
+    Armored,
+    Fast,
+    Splitting,
+    Shielded,
+    Vampiric,
+    Explosive,
+}
+
+const AFFIXES: [Affix; 6] = [
+    Affix::Armored,
+    Affix::Fast,
+    Affix::Splitting,
+    Affix::Shielded,
+    Affix::Vampiric,
+    Affix::Explosive,
+];
+
+#[derive(Component)]
+pub struct Elite {
+    pub affixes: Vec<Affix>,
+}
+
+// Model materials were already tinted with affix color
+#[derive(Component)]
+struct Tinted;
+
+// Chance of promotion grows with every wave, difficulty scales it
+#[derive(Resource)]
+pub struct EliteSettings {
+    pub base_chance: f32,
+    pub chance_per_wave: f32,
+    pub max_chance: f32,
+    pub waves_per_affix: u32,
+    pub max_affixes: usize,
+    pub score_per_affix: u32,
+}
+
+impl Default for EliteSettings {
+    fn default() -> Self {
+        EliteSettings {
+            base_chance: 0.05,
+            chance_per_wave: 0.03,
+            max_chance: 0.5,
+            waves_per_affix: 3,
+            max_affixes: 3,
+            score_per_affix: 15,
+        }
+    }
+}
+
+pub struct ElitesPlugin;
+impl Plugin for ElitesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EliteSettings>()
+            .add_systems(
+                Update,
+                (
+                    setup_elites,
+                    tint_elites,
+                    vampiric_elites.after(apply_damage),
+                    elite_death_effects.after(apply_damage),
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+impl Affix {
+    fn color(&self) -> Color {
+        match self {
+            Affix::Armored => Color::GRAY,
+            Affix::Fast => Color::YELLOW,
+            Affix::Splitting => Color::GREEN,
+            Affix::Shielded => Color::CYAN,
+            Affix::Vampiric => Color::CRIMSON,
+            Affix::Explosive => Color::ORANGE_RED,
+        }
+    }
+}
+
+pub fn roll_elite(settings: &EliteSettings, wave: u32, difficulty: Difficulty) -> Option<Elite> {
+    let mut rng = rand::thread_rng();
+    let chance = (settings.base_chance + settings.chance_per_wave * wave as f32)
+        * difficulty.multiplier();
+    if rng.gen::<f32>() >= chance.min(settings.max_chance) {
+        return None;
+    }
+
+    // Later waves allow elites with more affixes
+    let allowed = (1 + (wave / settings.waves_per_affix) as usize).min(settings.max_affixes);
+    let count = rng.gen_range(1..=allowed);
+    let affixes = AFFIXES.choose_multiple(&mut rng, count).copied().collect();
+    Some(Elite { affixes })
+}
+
+fn setup_elites(
+    mut elites: Query<(Entity, &Elite, &Health, &mut Armor, &mut Transform, &mut AiBrain, &mut ScoreValue), Added<Elite>>,
+    settings: Res<EliteSettings>,
+    mut commands: Commands,
+) {
+    for (entity, elite, health, mut armor, mut transform, mut brain, mut score_value) in elites.iter_mut() {
+        for affix in elite.affixes.iter() {
+            match affix {
+                // Plating stops part of every hit and most of bullets, energy still goes through
+                Affix::Armored => {
+                    armor.flat += 3;
+                    armor.add_resistance(DamageType::Kinetic, 0.25);
+                    armor.add_resistance(DamageType::Explosive, 0.25);
+                }
+                Affix::Fast => brain.profile.speed *= 1.5,
+                Affix::Shielded => {
+                    commands.entity(entity).insert(Shield(3));
+                }
+                _ => {}
+            }
+        }
+
+        // Vampiric healing can't go past health elite spawned with
+        commands.entity(entity).insert(MaxHealth(health.0));
+
+        transform.scale = Vec3::splat(1.3);
+        score_value.0 += settings.score_per_affix * elite.affixes.len() as u32;
+
+        // Glow in color of the main affix
+        let color = elite.affixes[0].color();
+        commands.entity(entity).with_children(|children| {
+            children.spawn(PointLightBundle {
+                point_light: PointLight {
+                    color,
+                    intensity: 500.0,
+                    range: 4.0,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 1.5, 0.0),
+                ..default()
+            });
+        });
+    }
+}
+
+// Model scene spawns its meshes a few frames after the enemy, so keep trying until there are materials to tint
+fn tint_elites(
+    elites: Query<(Entity, &Elite), Without<Tinted>>,
+    children: Query<&Children>,
+    mut meshes: Query<&mut Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for (entity, elite) in elites.iter() {
+        let [tint_r, tint_g, tint_b, _] = elite.affixes[0].color().as_rgba_f32();
+        let mut tinted = false;
+        for descendant in children.iter_descendants(entity) {
+            let Ok(mut material) = meshes.get_mut(descendant) else {
+                continue;
+            };
+            // Copy the material, so normal enemies sharing it keep their colors
+            let Some(mut copy) = materials.get(&material).cloned() else {
+                continue;
+            };
+            let [r, g, b, a] = copy.base_color.as_rgba_f32();
+            copy.base_color = Color::rgba((r + tint_r) / 2.0, (g + tint_g) / 2.0, (b + tint_b) / 2.0, a);
+            *material = materials.add(copy);
+            tinted = true;
+        }
+        if tinted {
+            commands.entity(entity).insert(Tinted);
+        }
+    }
+}
+
+// Vampiric elites heal themselves by damage they actually dealt to the player, blocked hits heal nothing
+fn vampiric_elites(
+    mut elites: Query<(&Elite, &mut Health, &MaxHealth), Without<Player>>,
+    player: Query<Entity, With<Player>>,
+    mut damage_number_event_reader: EventReader<DamageNumberEvent>,
+) {
+    for event in damage_number_event_reader.iter() {
+        if player.get_single().ok() != Some(event.target) {
+            continue;
+        }
+        let Some(source) = event.source else {
+            continue;
+        };
+        if let Ok((elite, mut health, max_health)) = elites.get_mut(source) {
+            if elite.affixes.contains(&Affix::Vampiric) {
+                health.0 = (health.0 + event.amount).min(max_health.0);
+            }
+        }
+    }
+}
+
+fn elite_death_effects(
+    elites: Query<(&Elite, &Enemy)>,
+    player: Query<Entity, With<Player>>,
+    mut death_event_reader: EventReader<DeathEvent>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
+    mut explosion_event_writer: EventWriter<ExplosionEvent>,
+    rapier_context: Res<RapierContext>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for event in death_event_reader.iter() {
+        let Ok((elite, enemy)) = elites.get(event.entity) else {
+            continue;
+        };
+
+        if elite.affixes.contains(&Affix::Splitting) {
+            // Split into two weaker copies
+            for offset in [-0.8, 0.8] {
+                let position = event.translation + Vec3::new(offset, 0.0, 0.0);
+                let child = spawn_enemy(enemy.enemy_type, position, &mut commands, &asset_server);
+                commands.entity(child).insert(Health(10));
+            }
+        }
+
+        if elite.affixes.contains(&Affix::Explosive) {
+            floating_text_event_writer.send(FloatingTextEvent {
+                translation: event.translation,
+                text: "Boom!".into(),
+                color: Color::ORANGE_RED,
+                size: 40.0,
+            });
+
+            explosion_event_writer.send(ExplosionEvent {
+                translation: event.translation,
+                radius: 2.5,
+            });
+
+            let shape = Collider::ball(2.5);
+            let filter = QueryFilter::default();
+            rapier_context.intersections_with_shape(event.translation, Quat::IDENTITY, &shape, filter, |entity| {
+                if player.get_single().ok() == Some(entity) {
+                    damage_event_writer.send(DamageEvent {
+                        target: entity,
+                        source: None,
+                        amount: 20,
+                        damage_type: DamageType::Explosive,
+                        crit_chance: 0.0,
+                        over_time: false,
+                    });
+                }
+                true
+            });
+        }
+    }
+}
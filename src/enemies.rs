@@ -6,13 +6,22 @@ use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
 use crate::player::Player;
-use crate::{GameState, Health, FloatingTextEvent};
+use crate::navigation::{NavGrid, PathFollower};
+use crate::steering::Steering;
+use crate::ai::{AiBrain, AiProfile, AiState};
+use crate::perception::Perception;
+use crate::{GameState, Health, Difficulty};
+use crate::combat::{Armor, DamageEvent, DamageType, ScoreValue};
+use crate::elites::{roll_elite, EliteSettings};
+use crate::animation::{AnimationController, AnimationKind};
+use crate::status::{StatusEffect, StatusEffects, StatusEvent, StatusKind};
 
 #[derive(Component)]
 struct EnemyBullet{
     shooter: Entity,
     direction: Vec3,
-    start_position: Vec3
+    start_position: Vec3,
+    status: Option<StatusEffect>,
 }
 
 pub enum EnemyState {
@@ -20,7 +29,7 @@ pub enum EnemyState {
     Cooldown,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum EnemyType {
     Chasing,
     Pistol,
@@ -30,30 +39,37 @@ pub enum EnemyType {
 
 #[derive(Component)]
 pub struct Enemy {
-    enemy_type: EnemyType,
-    enemy_state: EnemyState
+    pub enemy_type: EnemyType,
+    enemy_state: EnemyState,
+    // Own cooldown, so slowing one enemy doesn't slow the others
+    attack_timer: Timer,
 }
 
 #[derive(Resource)]
-struct EnemyAttackTimer(Timer);
+struct EnemySpawnTimer(Timer);
 
 #[derive(Resource)]
-struct EnemySpawnTimer(Timer);
+pub struct Wave(pub u32);
+
+#[derive(Resource)]
+struct WaveTimer(Timer);
 
 pub struct EnemiesPlugin;
 impl Plugin for EnemiesPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(EnemyAttackTimer(Timer::from_seconds(
-            2.0,
+        app.insert_resource(EnemySpawnTimer(Timer::from_seconds(
+            7.0,
             TimerMode::Repeating,
         )))
-        .insert_resource(EnemySpawnTimer(Timer::from_seconds(
-            7.0,
+        .insert_resource(WaveTimer(Timer::from_seconds(
+            30.0,
             TimerMode::Repeating,
         )))
+        .insert_resource(Wave(1))
         .add_systems(
             Update,
             (
+                advance_wave,
                 spawn_enemies,
                 rotate_enemies,
                 move_enemies,
@@ -66,49 +82,73 @@ impl Plugin for EnemiesPlugin {
     }
 }
 
+fn advance_wave(
+    time: Res<Time>,
+    mut timer: ResMut<WaveTimer>,
+    mut wave: ResMut<Wave>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        wave.0 += 1;
+    }
+}
+
 fn spawn_enemies(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
     time: Res<Time>,
     mut timer: ResMut<EnemySpawnTimer>,
+    wave: Res<Wave>,
+    difficulty: Res<Difficulty>,
+    elite_settings: Res<EliteSettings>,
 ) { 
     if !timer.0.tick(time.delta()).finished() {
         return;
     }
 
     let mut rng = rand::thread_rng();
-    match rng.gen_range(1..=4) {
-        1 => {
-            spawn_enemy(EnemyType::Chasing, "models/characterZombie.glb#Scene0", &mut commands, &asset_server)
-        },
-        2 => {
-            spawn_enemy(EnemyType::Pistol, "models/characterSkeleton.glb#Scene0", &mut commands, &asset_server)
-        }
-        3 => {
-            spawn_enemy(EnemyType::Shotgun, "models/characterGhost.glb#Scene0", &mut commands, &asset_server)
-        },
-        4 => {
-            spawn_enemy(EnemyType::Star, "models/characterVampire.glb#Scene0", &mut commands, &asset_server)
-        },
+    let enemy_type = match rng.gen_range(1..=4) {
+        1 => EnemyType::Chasing,
+        2 => EnemyType::Pistol,
+        3 => EnemyType::Shotgun,
+        4 => EnemyType::Star,
         _ => unreachable!()
+    };
+
+    let angle: f32 = rng.gen_range(0.0..1.0) * PI * 2.0;
+    let position = Vec3::new(angle.sin() * 7.0, 1.0, angle.cos() * 7.0);
+    let enemy_entity = spawn_enemy(enemy_type, position, &mut commands, &asset_server);
+
+    // Promote some enemies to elites, more often in later waves
+    if let Some(elite) = roll_elite(&elite_settings, wave.0, *difficulty) {
+        commands.entity(enemy_entity).insert(elite);
     }
 }
 
-fn spawn_enemy(
+pub fn spawn_enemy(
     enemy_type: EnemyType,
-    model: &str,
+    position: Vec3,
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
-) {
-    let mut rng = rand::thread_rng();
-    let angle: f32 = rng.gen_range(0.0..1.0) * PI * 2.0;
-    let x = angle.sin() * 7.0;
-    let z = angle.cos() * 7.0;
+) -> Entity {
+    let model = match enemy_type {
+        EnemyType::Chasing => "models/characterZombie.glb#Scene0",
+        EnemyType::Pistol => "models/characterSkeleton.glb#Scene0",
+        EnemyType::Shotgun => "models/characterGhost.glb#Scene0",
+        EnemyType::Star => "models/characterVampire.glb#Scene0",
+    };
+    let steering = steering_for(&enemy_type);
+    let brain = AiBrain::new(ai_profile_for(&enemy_type));
+    let perception = perception_for(&enemy_type);
     commands
-        .spawn(Enemy{enemy_state: EnemyState::Attacking, enemy_type: enemy_type})
-        .insert(Health(3))
+        .spawn(Enemy{
+            enemy_state: EnemyState::Attacking,
+            enemy_type: enemy_type,
+            attack_timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+        })
+        .insert(Health(30))
+        .insert(ScoreValue(10))
         .insert(PbrBundle {
-            transform: Transform::from_xyz(x, 1.0, z),
+            transform: Transform::from_translation(position),
             ..default()
         })
         .with_children(|cell| {
@@ -124,60 +164,190 @@ fn spawn_enemy(
         })
         .insert(RigidBody::Dynamic)
         .insert(Velocity::zero())
-        .insert(Collider::capsule_y(0.5, 0.5));
+        .insert(Collider::capsule_y(0.5, 0.5))
+        .insert(steering)
+        .insert(brain)
+        .insert(perception)
+        .insert(PathFollower::new(0.5))
+        .insert(AnimationController::new())
+        .insert(StatusEffects::new(immunities_for(&enemy_type)))
+        .insert(armor_for(&enemy_type))
+        .id()
+}
+
+// Bones shrug off bullets but shatter from blasts, ghosts are mostly immaterial
+fn armor_for(enemy_type: &EnemyType) -> Armor {
+    match enemy_type {
+        EnemyType::Chasing => Armor::new(0, &[(DamageType::Fire, -0.5)]),
+        EnemyType::Pistol => Armor::new(3, &[(DamageType::Kinetic, 0.25), (DamageType::Explosive, -0.5)]),
+        EnemyType::Shotgun => Armor::new(0, &[(DamageType::Kinetic, 0.5), (DamageType::Energy, -0.5)]),
+        EnemyType::Star => Armor::new(2, &[(DamageType::Fire, -0.5), (DamageType::Energy, 0.25)]),
+    }
+}
+
+// Undead don't care about poison, ghosts can't be slowed either
+fn immunities_for(enemy_type: &EnemyType) -> &'static [StatusKind] {
+    match enemy_type {
+        EnemyType::Chasing | EnemyType::Pistol | EnemyType::Star => &[StatusKind::Poison],
+        EnemyType::Shotgun => &[StatusKind::Poison, StatusKind::Slow],
+    }
+}
+
+// Ghost shots chill the player
+fn bullet_status_for(enemy_type: &EnemyType) -> Option<StatusEffect> {
+    match enemy_type {
+        EnemyType::Shotgun => Some(StatusEffect {
+            kind: StatusKind::Slow,
+            duration: 1.5,
+        }),
+        _ => None,
+    }
+}
+
+// Zombies swarm and flank, shooters keep more personal space
+fn steering_for(enemy_type: &EnemyType) -> Steering {
+    match enemy_type {
+        EnemyType::Chasing => Steering {
+            separation_radius: 1.5,
+            separation_weight: 1.2,
+            avoidance_distance: 1.5,
+            avoidance_weight: 2.0,
+            surround_radius: 1.5,
+            flank_angle: Steering::random_flank(0.8),
+        },
+        EnemyType::Pistol => Steering {
+            separation_radius: 2.5,
+            separation_weight: 1.0,
+            avoidance_distance: 1.5,
+            avoidance_weight: 2.0,
+            surround_radius: 0.0,
+            flank_angle: 0.0,
+        },
+        EnemyType::Shotgun => Steering {
+            separation_radius: 2.0,
+            separation_weight: 1.0,
+            avoidance_distance: 1.5,
+            avoidance_weight: 2.0,
+            surround_radius: 0.0,
+            flank_angle: 0.0,
+        },
+        EnemyType::Star => Steering {
+            separation_radius: 3.0,
+            separation_weight: 1.0,
+            avoidance_distance: 1.5,
+            avoidance_weight: 2.0,
+            surround_radius: 0.0,
+            flank_angle: 0.0,
+        },
+    }
+}
+
+// Zombies rush in, shooters keep their preferred distance and flee when almost dead
+fn ai_profile_for(enemy_type: &EnemyType) -> AiProfile {
+    match enemy_type {
+        EnemyType::Chasing => AiProfile {
+            speed: 200.0,
+            attack_range: 1.4,
+            preferred_distance: 0.0,
+            distance_tolerance: 0.0,
+            flee_health: 0,
+            strafes: false,
+        },
+        EnemyType::Pistol => AiProfile {
+            speed: 120.0,
+            attack_range: 10.0,
+            preferred_distance: 6.0,
+            distance_tolerance: 1.5,
+            flee_health: 10,
+            strafes: true,
+        },
+        EnemyType::Shotgun => AiProfile {
+            speed: 150.0,
+            attack_range: 6.0,
+            preferred_distance: 4.0,
+            distance_tolerance: 1.0,
+            flee_health: 10,
+            strafes: true,
+        },
+        EnemyType::Star => AiProfile {
+            speed: 100.0,
+            attack_range: 5.0,
+            preferred_distance: 3.0,
+            distance_tolerance: 1.0,
+            flee_health: 0,
+            strafes: false,
+        },
+    }
+}
+
+// Zombies sense player anywhere, shooters only see what is in front of them
+fn perception_for(enemy_type: &EnemyType) -> Perception {
+    match enemy_type {
+        EnemyType::Chasing => Perception::new(30.0, PI, 5.0),
+        EnemyType::Pistol => Perception::new(15.0, 1.0, 4.0),
+        EnemyType::Shotgun => Perception::new(12.0, 1.2, 4.0),
+        EnemyType::Star => Perception::new(10.0, 1.5, 3.0),
+    }
 }
 
 fn enemy_shoot_attack(
     mut player: Query<(&Transform, &mut Health), (With<Player>, Without<Enemy>)>,
     mut enemies: Query<
-        (Entity, &Transform, &mut Enemy),
+        (Entity, &Transform, &mut Enemy, &AiBrain, &Perception, &mut AnimationController, &StatusEffects),
         (With<Enemy>, Without<Player>),
     >,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     time: Res<Time>,
-    mut timer: ResMut<EnemyAttackTimer>,
 ) {
-    for (enemy_entity, enemy_transform, mut enemy) in enemies.iter_mut() {
-        if enemy.enemy_type == EnemyType::Chasing {
+    for (enemy_entity, enemy_transform, mut enemy, brain, perception, mut animation, status_effects) in enemies.iter_mut() {
+        if enemy.enemy_type == EnemyType::Chasing || !status_effects.can_act() {
             continue
         }
+        let status = bullet_status_for(&enemy.enemy_type);
 
         let direction =
             (player.single_mut().0.translation - enemy_transform.translation).normalize();
 
         match enemy.enemy_state {
             EnemyState::Attacking => {
+                // Hold the shot until brain decides to attack and there is clear line to player
+                if !brain.can_fire() || !perception.can_see_player {
+                    continue;
+                }
 
                 match enemy.enemy_type {
                     EnemyType::Pistol => {
-                        spawn_bullet(enemy_transform.translation, direction, enemy_entity, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, direction, enemy_entity, status, &mut meshes, &mut materials, &mut commands);
                     }
                     EnemyType::Shotgun => {
-                        spawn_bullet(enemy_transform.translation, Quat::from_rotation_y(-0.3) * direction, enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, direction, enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Quat::from_rotation_y(0.3) * direction, enemy_entity, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Quat::from_rotation_y(-0.3) * direction, enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, direction, enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Quat::from_rotation_y(0.3) * direction, enemy_entity, status, &mut meshes, &mut materials, &mut commands);
                     }
                     EnemyType::Star => {
-                        spawn_bullet(enemy_transform.translation, Vec3::new(1.0, 0.0, 0.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(0.0, 0.0, 1.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(0.0, 0.0, -1.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(-1.0, 0.0, 0.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(1.0, 0.0, 1.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(-1.0, 0.0, 1.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(1.0, 0.0, -1.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
-                        spawn_bullet(enemy_transform.translation, Vec3::new(-1.0, 0.0, -1.0), enemy_entity, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(1.0, 0.0, 0.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(0.0, 0.0, 1.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(0.0, 0.0, -1.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(-1.0, 0.0, 0.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(1.0, 0.0, 1.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(-1.0, 0.0, 1.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(1.0, 0.0, -1.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
+                        spawn_bullet(enemy_transform.translation, Vec3::new(-1.0, 0.0, -1.0), enemy_entity, status, &mut meshes, &mut materials, &mut commands);
                         
                     }
                     _ => {}
                 }
 
+                animation.play(AnimationKind::Attack);
                 enemy.enemy_state = EnemyState::Cooldown;
             }
             _ => {
                 // Enemy attack cooldown
-                if !timer.0.tick(time.delta()).finished() {
+                // Slowed enemies attack less often
+                let delta = time.delta().mul_f32(status_effects.attack_speed_multiplier());
+                if !enemy.attack_timer.tick(delta).finished() {
                     continue;
                 }
                 enemy.enemy_state = EnemyState::Attacking;
@@ -190,6 +360,7 @@ fn spawn_bullet(
     origin: Vec3,
     direction: Vec3,
     shooter: Entity,
+    status: Option<StatusEffect>,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     commands: &mut Commands,
@@ -209,7 +380,8 @@ fn spawn_bullet(
         .insert(EnemyBullet{
             shooter,
             direction,
-            start_position: origin
+            start_position: origin,
+            status,
         })
         .insert(RigidBody::Dynamic)
         .insert(Velocity::zero());
@@ -220,10 +392,12 @@ fn move_enemy_bullets(
         (Entity, &mut Velocity, &EnemyBullet, &Transform),
         With<EnemyBullet>,
     >,
-    mut player: Query<(Entity, &mut Health), With<Player>>,
+    player: Query<Entity, With<Player>>,
     rapier_context: Res<RapierContext>,
     mut commands: Commands,
     time: Res<Time>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut status_event_writer: EventWriter<StatusEvent>,
 ) {
     const SPEED: f32 = 600.0;
     for (bullet_entity, mut vel, bullet_struct, transform) in bullets.iter_mut() {
@@ -251,23 +425,37 @@ fn move_enemy_bullets(
             // Despawn bullet after it hits anything
             commands.entity(bullet_entity).despawn_recursive();
 
-            if entity == player.single().0 {
-                player.single_mut().1 .0 -= 1;
+            if entity == player.single() {
+                damage_event_writer.send(DamageEvent {
+                    target: entity,
+                    source: Some(bullet_struct.shooter),
+                    amount: 10,
+                    damage_type: DamageType::Kinetic,
+                    crit_chance: 0.0,
+                    over_time: false,
+                });
+                if let Some(effect) = bullet_struct.status {
+                    status_event_writer.send(StatusEvent {
+                        target: entity,
+                        source: Some(bullet_struct.shooter),
+                        effect,
+                    });
+                }
             }
         }
     }
 }
 
 fn enemy_melee_attack(
-    mut enemies: Query<(&Transform, &mut Enemy), With<Enemy>>,
-    mut player: Query<(Entity, &mut Health, &Transform), With<Player>>,
+    mut enemies: Query<(Entity, &Transform, &mut Enemy, &mut AnimationController, &StatusEffects), With<Enemy>>,
+    player: Query<Entity, With<Player>>,
     rapier_context: Res<RapierContext>,
     time: Res<Time>,
-    mut timer: ResMut<EnemyAttackTimer>,
-    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut status_event_writer: EventWriter<StatusEvent>,
 ) {
-    for (enemy_transform, mut enemy) in enemies.iter_mut() {
-        if enemy.enemy_type != EnemyType::Chasing {
+    for (enemy_entity, enemy_transform, mut enemy, mut animation, status_effects) in enemies.iter_mut() {
+        if enemy.enemy_type != EnemyType::Chasing || !status_effects.can_act() {
             continue
         }
 
@@ -278,7 +466,7 @@ fn enemy_melee_attack(
 
         let mut player_is_hit = false;
         rapier_context.intersections_with_shape(shape_pos, shape_rot, &shape, filter, |entity| {
-            if entity == player.single().0 {
+            if entity == player.single() {
                 player_is_hit = true;
             }
             true
@@ -288,20 +476,31 @@ fn enemy_melee_attack(
             match enemy.enemy_state {
                 EnemyState::Attacking => {
                     // Attack player
-                    player.single_mut().1.0 -= 1;
-
-                    // Create floating text
-                    floating_text_event_writer.send(FloatingTextEvent {
-                        translation: player.single_mut().2.translation,
-                        text: "-1".into(),
-                        color: Color::rgb(0.7, 0.0, 0.0),
+                    damage_event_writer.send(DamageEvent {
+                        target: player.single(),
+                        source: Some(enemy_entity),
+                        amount: 10,
+                        damage_type: DamageType::Kinetic,
+                        crit_chance: 0.0,
+                        over_time: false,
+                    });
+                    // Zombie bite is poisonous
+                    status_event_writer.send(StatusEvent {
+                        target: player.single(),
+                        source: Some(enemy_entity),
+                        effect: StatusEffect {
+                            kind: StatusKind::Poison,
+                            duration: 3.0,
+                        },
                     });
 
+                    animation.play(AnimationKind::Attack);
                     enemy.enemy_state = EnemyState::Cooldown;
                 }
                 _ => {
                     // Enemy attack cooldown
-                    if !timer.0.tick(time.delta()).finished() {
+                    let delta = time.delta().mul_f32(status_effects.attack_speed_multiplier());
+                    if !enemy.attack_timer.tick(delta).finished() {
                         continue;
                     }
                     enemy.enemy_state = EnemyState::Attacking;
@@ -312,13 +511,23 @@ fn enemy_melee_attack(
 }
 
 fn rotate_enemies(
-    mut enemies: Query<&mut Transform, (With<Enemy>, Without<Player>)>,
+    mut enemies: Query<(&mut Transform, &Velocity, &Perception, &StatusEffects), (With<Enemy>, Without<Player>)>,
     mut player_transform: Query<&Transform, (With<Player>, Without<Enemy>)>,
 ) {
-    for mut enemy_transform in enemies.iter_mut() {
-        // Get vector representing direction from enemy to player
-        let mut direction_vec =
-            player_transform.single_mut().translation - enemy_transform.translation;
+    for (mut enemy_transform, enemy_velocity, perception, status_effects) in enemies.iter_mut() {
+        if !status_effects.can_move() {
+            continue;
+        }
+        // Get vector representing direction to player if enemy knows about him, otherwise direction it's moving
+        let mut direction_vec = if perception.is_aware() {
+            player_transform.single_mut().translation - enemy_transform.translation
+        } else {
+            enemy_velocity.linvel
+        };
+        direction_vec.y = 0.0;
+        if direction_vec.length() < 0.01 {
+            continue;
+        }
         direction_vec = direction_vec.normalize();
 
         // Rotate ememy in direction of player
@@ -327,34 +536,59 @@ fn rotate_enemies(
     }
 }
 
-fn move_enemies(
-    mut enemies: Query<(&Transform, &mut Velocity, &mut Enemy), (With<Enemy>, Without<Player>)>,
+pub fn move_enemies(
+    mut enemies: Query<
+        (Entity, &Transform, &mut Velocity, &mut PathFollower, &Steering, &AiBrain, &Perception, &StatusEffects),
+        (With<Enemy>, Without<Player>),
+    >,
+    neighbours: Query<(Entity, &Transform), (With<Steering>, Without<Player>)>,
     mut player_transform: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    nav_grid: Res<NavGrid>,
+    rapier_context: Res<RapierContext>,
     time: Res<Time>,
 ) {
-    const SPEED: f32 = 200.0;
-    for (enemy_transform, mut enemy_velocity, enemy) in enemies.iter_mut() {
-        if enemy.enemy_type != EnemyType::Chasing {
-            continue
-        }
+    let neighbours: Vec<(Entity, Vec3)> = neighbours
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation))
+        .collect();
+
+    for (enemy_entity, enemy_transform, mut enemy_velocity, mut path_follower, steering, brain, perception, status_effects) in enemies.iter_mut() {
+        // Enemy only goes where it last saw the player
+        let player_pos = perception
+            .last_seen_position
+            .unwrap_or(player_transform.single_mut().translation);
+        let enemy_pos = enemy_transform.translation;
+        let away_from_player = Vec3::new(enemy_pos.x - player_pos.x, 0.0, enemy_pos.z - player_pos.z).normalize_or_zero();
+        let sideways = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2) * away_from_player * brain.strafe_sign;
+
+        // Backing away into wall or off the edge of the ground turns into sidestep
+        let backing_off = if nav_grid.is_walkable(enemy_pos + away_from_player) {
+            away_from_player
+        } else {
+            sideways
+        };
 
-        // Get vector representing direction from enemy to player
-        let mut direction_vec =
-            player_transform.single_mut().translation - enemy_transform.translation;
-        direction_vec = direction_vec.normalize();
+        // Pick direction and speed according to what the brain decided
+        let (desired_direction, speed) = match brain.state {
+            AiState::Idle | AiState::Attack => (Vec3::ZERO, brain.profile.speed),
+            AiState::Patrol => {
+                path_follower.target = brain.patrol_point;
+                (path_follower.direction(enemy_pos), brain.profile.speed * 0.5)
+            }
+            AiState::Chase => {
+                // Get direction along path leading around obstacles to player, approaching from enemy's own angle
+                path_follower.target = steering.approach_point(enemy_pos, player_pos);
+                (path_follower.direction(enemy_pos), brain.profile.speed)
+            }
+            AiState::Strafe => (sideways, brain.profile.speed * 0.7),
+            AiState::Retreat => (backing_off, brain.profile.speed * 0.8),
+            AiState::Flee => (backing_off, brain.profile.speed * 1.3),
+        };
 
-        // Calculate distance of vectors, so enemy chases player only until it's near him
-        let vec2_player = Vec2::new(
-            player_transform.single_mut().translation[0],
-            player_transform.single_mut().translation[2],
-        );
-        let vec2_enemy = Vec2::new(
-            enemy_transform.translation[0],
-            enemy_transform.translation[2],
-        );
-        if vec2_player.distance(vec2_enemy) > 1.4 {
-            enemy_velocity.linvel[0] = direction_vec[0] * SPEED * time.delta_seconds();
-            enemy_velocity.linvel[2] = direction_vec[2] * SPEED * time.delta_seconds();
-        }
+        // Standing enemies still keep spreading out around the player
+        let direction_vec = steering.steer(enemy_entity, enemy_pos, desired_direction, &neighbours, &rapier_context);
+        let speed = speed * status_effects.speed_multiplier();
+        enemy_velocity.linvel[0] = direction_vec[0] * speed * time.delta_seconds();
+        enemy_velocity.linvel[2] = direction_vec[2] * speed * time.delta_seconds();
     }
 }
@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+
+use crate::player::Player;
+use crate::bosses::Boss;
+use crate::camera::move_camera;
+use crate::floating_text::DamageNumberEvent;
+use crate::settings::Settings;
+use crate::{GameState, MainCamera};
+
+// Sent by anything that blows up, shakes more the closer it is to the player
+#[derive(Event)]
+pub struct ExplosionEvent {
+    pub translation: Vec3,
+    pub radius: f32,
+}
+
+// Camera shake amount between 0 and 1, shake grows with its square
+#[derive(Resource, Default)]
+pub struct Trauma(pub f32);
+
+impl Trauma {
+    pub fn add(&mut self, amount: f32) {
+        self.0 = (self.0 + amount).min(1.0);
+    }
+}
+
+// Real time left of the current hit-stop
+#[derive(Resource, Default)]
+struct HitStop(f32);
+
+// Trauma lost per second
+const TRAUMA_DECAY: f32 = 1.5;
+const MAX_SHAKE_OFFSET: f32 = 0.5;
+const MAX_SHAKE_ROLL: f32 = 0.05;
+const SHAKE_FREQUENCY: f32 = 25.0;
+// Hits dealing at least this much, or crits, freeze the game for a moment, damage over time never does
+const HEAVY_HIT: i32 = 30;
+const HIT_STOP_DURATION: f32 = 0.06;
+const HIT_STOP_SPEED: f32 = 0.05;
+
+pub struct FeedbackPlugin;
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trauma>()
+            .init_resource::<HitStop>()
+            .add_event::<ExplosionEvent>()
+            .add_systems(
+                Update,
+                (
+                    shake_on_damage,
+                    shake_on_explosions,
+                    shake_on_boss_spawn,
+                    shake_camera.after(move_camera),
+                    hit_stop_on_heavy_hits,
+                    update_hit_stop.after(hit_stop_on_heavy_hits),
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+fn shake_on_damage(
+    player: Query<Entity, With<Player>>,
+    mut damage_number_event_reader: EventReader<DamageNumberEvent>,
+    mut trauma: ResMut<Trauma>,
+) {
+    let player = player.get_single().ok();
+    for event in damage_number_event_reader.iter() {
+        // Burn and poison ticks would keep the camera shaking
+        if Some(event.target) == player && !event.over_time {
+            trauma.add(0.2 + event.amount as f32 * 0.01);
+        }
+    }
+}
+
+fn shake_on_explosions(
+    player: Query<&Transform, With<Player>>,
+    mut explosion_event_reader: EventReader<ExplosionEvent>,
+    mut trauma: ResMut<Trauma>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    for event in explosion_event_reader.iter() {
+        // Full shake inside the blast, none from far away
+        let reach = event.radius * 4.0;
+        let distance = player.translation.distance(event.translation);
+        trauma.add(0.5 * (1.0 - distance / reach).clamp(0.0, 1.0));
+    }
+}
+
+fn shake_on_boss_spawn(bosses: Query<(), Added<Boss>>, mut trauma: ResMut<Trauma>) {
+    if !bosses.is_empty() {
+        trauma.add(0.6);
+    }
+}
+
+// Runs after camera rig placed the camera, so offset is never accumulated
+pub fn shake_camera(
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    mut trauma: ResMut<Trauma>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    // Real time, so shake settles during hit-stop too
+    trauma.0 = (trauma.0 - TRAUMA_DECAY * time.raw_delta_seconds()).max(0.0);
+    if !settings.screen_shake || trauma.0 == 0.0 {
+        return;
+    }
+
+    // Sines of unrelated frequencies are smooth but don't look repetitive
+    let t = time.raw_elapsed_seconds() * SHAKE_FREQUENCY;
+    let wave = |phase: f32| ((t + phase).sin() + (t * 0.57 + phase * 2.3).sin()) / 2.0;
+    let shake = trauma.0 * trauma.0;
+
+    let mut transform = camera.single_mut();
+    let offset = transform.right() * wave(0.0) + transform.up() * wave(10.0);
+    transform.translation += offset * MAX_SHAKE_OFFSET * shake;
+    transform.rotate_local_z(wave(20.0) * MAX_SHAKE_ROLL * shake);
+}
+
+fn hit_stop_on_heavy_hits(
+    mut damage_number_event_reader: EventReader<DamageNumberEvent>,
+    mut hit_stop: ResMut<HitStop>,
+    settings: Res<Settings>,
+) {
+    // Read every event, stopping at first heavy one would leave the rest for next frame
+    let heavy = damage_number_event_reader
+        .iter()
+        .filter(|event| !event.over_time && (event.critical || event.amount >= HEAVY_HIT))
+        .count()
+        > 0;
+    if heavy && settings.hit_stop {
+        hit_stop.0 = HIT_STOP_DURATION;
+    }
+}
+
+// Slow down virtual time while hit-stop lasts, counting it down in real time
+fn update_hit_stop(mut hit_stop: ResMut<HitStop>, settings: Res<Settings>, mut time: ResMut<Time>) {
+    hit_stop.0 = (hit_stop.0 - time.raw_delta_seconds()).max(0.0);
+    let speed = if hit_stop.0 > 0.0 && settings.hit_stop {
+        HIT_STOP_SPEED
+    } else {
+        1.0
+    };
+    if time.relative_speed() != speed {
+        time.set_relative_speed(speed);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::feedback::shake_camera;
+use crate::{FloatingTextEvent, GameState, MainCamera};
+
+// Damage number that sticks to its target and sums up quick hits
+#[derive(Event)]
+pub struct DamageNumberEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub translation: Vec3,
+    pub amount: i32,
+    pub color: Color,
+    pub critical: bool,
+    pub over_time: bool,
+}
+
+#[derive(Component)]
+struct FloatingText {
+    // World position text rises from, follows target while it is alive
+    anchor: Vec3,
+    target: Option<Entity>,
+    amount: i32,
+    critical: bool,
+    age: f32,
+    // Time since last hit was added, merging never restarts the animation
+    since_hit: f32,
+    time_to_live: f32,
+}
+
+const TEXT_TIME_TO_LIVE: f32 = 1.0;
+// World units text rises per second
+const RISE_SPEED: f32 = 1.2;
+// Hits on the same target within this time add up into one number
+const MERGE_WINDOW: f32 = 0.35;
+// Labels older than this start a new number, so steady fire can't keep one on screen
+const MAX_MERGE_AGE: f32 = 0.5;
+// Oldest labels are removed when there are more than this
+const MAX_LABELS: usize = 24;
+
+pub struct FloatingTextPlugin;
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FloatingTextEvent>()
+            .add_event::<DamageNumberEvent>()
+            .add_systems(
+                Update,
+                (
+                    spawn_floating_text,
+                    spawn_damage_numbers,
+                    // Shake runs after camera rig, so labels follow the final camera of this frame
+                    update_floating_text.after(shake_camera),
+                    limit_floating_text,
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+// Bigger hits get bigger numbers, crits on top of that
+fn damage_size(amount: i32, critical: bool) -> f32 {
+    let size = (26.0 + amount as f32 * 0.5).min(60.0);
+    if critical {
+        size * 1.4
+    } else {
+        size
+    }
+}
+
+fn damage_text(amount: i32, critical: bool) -> String {
+    if critical {
+        format!("-{}!", amount)
+    } else {
+        format!("-{}", amount)
+    }
+}
+
+fn spawn_label(
+    text: String,
+    color: Color,
+    size: f32,
+    floating_text: FloatingText,
+    asset_server: &AssetServer,
+    commands: &mut Commands,
+) {
+    // Hidden until it gets positioned on screen
+    commands
+        .spawn(
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: size,
+                    color,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                display: Display::None,
+                ..default()
+            }),
+        )
+        .insert(floating_text);
+}
+
+fn spawn_floating_text(
+    asset_server: Res<AssetServer>,
+    mut floating_text_event_reader: EventReader<FloatingTextEvent>,
+    mut commands: Commands,
+) {
+    for event in floating_text_event_reader.iter() {
+        let floating_text = FloatingText {
+            anchor: event.translation,
+            target: None,
+            amount: 0,
+            critical: false,
+            age: 0.0,
+            since_hit: 0.0,
+            time_to_live: TEXT_TIME_TO_LIVE,
+        };
+        spawn_label(event.text.clone(), event.color, event.size, floating_text, &asset_server, &mut commands);
+    }
+}
+
+fn spawn_damage_numbers(
+    mut labels: Query<(&mut FloatingText, &mut Text)>,
+    asset_server: Res<AssetServer>,
+    mut damage_number_event_reader: EventReader<DamageNumberEvent>,
+    mut commands: Commands,
+) {
+    // Sum up hits of this frame first, labels spawned now aren't queryable yet
+    let mut hits: HashMap<Entity, (Vec3, i32, Color, bool)> = HashMap::new();
+    for event in damage_number_event_reader.iter() {
+        let hit = hits
+            .entry(event.target)
+            .or_insert((event.translation, 0, event.color, false));
+        hit.1 += event.amount;
+        hit.3 |= event.critical;
+        if event.critical {
+            hit.2 = event.color;
+        }
+    }
+
+    for (target, (translation, amount, color, critical)) in hits {
+        let merged = labels
+            .iter_mut()
+            .find(|(label, _)| {
+                label.target == Some(target) && label.since_hit < MERGE_WINDOW && label.age < MAX_MERGE_AGE
+            });
+        if let Some((mut label, mut text)) = merged {
+            label.amount += amount;
+            label.critical |= critical;
+            label.since_hit = 0.0;
+            let section = &mut text.sections[0];
+            section.value = damage_text(label.amount, label.critical);
+            section.style.font_size = damage_size(label.amount, label.critical);
+            continue;
+        }
+
+        let floating_text = FloatingText {
+            anchor: translation,
+            target: Some(target),
+            amount,
+            critical,
+            age: 0.0,
+            since_hit: 0.0,
+            time_to_live: TEXT_TIME_TO_LIVE,
+        };
+        spawn_label(damage_text(amount, critical), color, damage_size(amount, critical), floating_text, &asset_server, &mut commands);
+    }
+}
+
+// Project every label from the world each frame, so it stays put while camera moves
+fn update_floating_text(
+    mut labels: Query<(Entity, &mut FloatingText, &mut Style, &mut Text)>,
+    targets: Query<&GlobalTransform, Without<FloatingText>>,
+    camera: Query<(&Camera, &Transform), With<MainCamera>>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    // Camera has no parent, so its Transform is up to date while GlobalTransform lags a frame
+    let (camera, camera_transform) = camera.single();
+    let camera_transform = GlobalTransform::from(*camera_transform);
+
+    for (entity, mut label, mut style, mut text) in labels.iter_mut() {
+        label.age += time.delta_seconds();
+        label.since_hit += time.delta_seconds();
+        if label.age > label.time_to_live {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let Some(target) = label.target.and_then(|target| targets.get(target).ok()) {
+            label.anchor = target.translation();
+        }
+
+        let position = label.anchor + Vec3::Y * RISE_SPEED * label.age;
+        match camera.world_to_viewport(&camera_transform, position) {
+            Some(coords) => {
+                style.display = Display::Flex;
+                style.top = Val::Px(coords.y);
+                style.left = Val::Px(coords.x);
+            }
+            None => style.display = Display::None,
+        }
+
+        // Fade out over the second half of its life
+        let alpha = (2.0 * (1.0 - label.age / label.time_to_live)).min(1.0);
+        text.sections[0].style.color.set_a(alpha);
+    }
+}
+
+fn limit_floating_text(labels: Query<(Entity, &FloatingText)>, mut commands: Commands) {
+    let count = labels.iter().count();
+    if count <= MAX_LABELS {
+        return;
+    }
+    let mut labels: Vec<(Entity, f32)> = labels.iter().map(|(entity, label)| (entity, label.age)).collect();
+    labels.sort_by(|a, b| b.1.total_cmp(&a.1));
+    for (entity, _) in labels.iter().take(count - MAX_LABELS) {
+        commands.entity(*entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::window::CursorMoved;
+
+use crate::player::Player;
+use crate::camera::camera_basis;
+use crate::{Cursor, GameState, MainCamera};
+
+// State of the gamepad player is using, buttons are turned into actions, sticks are read directly
+#[derive(Resource, Default)]
+pub struct GamepadControls {
+    gamepad: Option<Gamepad>,
+    // Gamepad was used after the mouse, so right stick aims instead of mouse
+    pub active: bool,
+    // Left stick with dead zone applied, y is up on the screen
+    pub movement: Vec2,
+    // Last direction aimed with right stick or walked with left stick
+    pub aim: Option<Vec2>,
+    pub buttons: Input<GamepadButtonType>,
+}
+
+// Sticks report small values when resting, ignore everything under this
+const MOVE_DEAD_ZONE: f32 = 0.2;
+// Aim needs to be pushed further, so direction doesn't snap back as stick returns to center
+const AIM_DEAD_ZONE: f32 = 0.4;
+// How far in front of the player cursor goes when aiming with stick
+const AIM_DISTANCE: f32 = 4.0;
+
+const BUTTONS: [GamepadButtonType; 10] = [
+    GamepadButtonType::South,
+    GamepadButtonType::East,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+];
+
+pub struct GamepadControlsPlugin;
+impl Plugin for GamepadControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GamepadControls>()
+            // Read right after bevy updates gamepads, so every Update system sees the same state
+            .add_systems(PreUpdate, read_gamepad.after(InputSystem))
+            .add_systems(Update, aim_with_gamepad.in_set(GameState::Playing));
+    }
+}
+
+// Scale stick so output starts from zero at the edge of dead zone instead of jumping
+fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length < dead_zone {
+        return Vec2::ZERO;
+    }
+    stick / length * ((length - dead_zone) / (1.0 - dead_zone)).min(1.0)
+}
+
+pub fn read_gamepad(
+    mut controls: ResMut<GamepadControls>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    buttons: Res<Input<GamepadButton>>,
+    mouse: Res<Input<MouseButton>>,
+    mut cursor_moved_er: EventReader<CursorMoved>,
+) {
+    // Keep using the same gamepad while it's connected, otherwise take any other one
+    if !controls.gamepad.map_or(false, |gamepad| gamepads.contains(gamepad)) {
+        controls.gamepad = gamepads.iter().next();
+        controls.buttons.reset_all();
+        controls.movement = Vec2::ZERO;
+        if let Some(gamepad) = controls.gamepad {
+            info!("Using gamepad {}", gamepads.name(gamepad).unwrap_or("unknown"));
+        }
+    }
+    controls.buttons.clear();
+
+    let Some(gamepad) = controls.gamepad else {
+        controls.active = false;
+        return;
+    };
+
+    for button_type in BUTTONS {
+        if buttons.pressed(GamepadButton::new(gamepad, button_type)) {
+            controls.buttons.press(button_type);
+        } else {
+            controls.buttons.release(button_type);
+        }
+    }
+
+    let stick = |x, y| {
+        Vec2::new(
+            axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.0),
+            axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.0),
+        )
+    };
+    controls.movement = apply_dead_zone(stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY), MOVE_DEAD_ZONE);
+    let aim = apply_dead_zone(stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY), AIM_DEAD_ZONE);
+
+    // Twin-stick style, face where you walk until right stick says otherwise
+    if aim != Vec2::ZERO {
+        controls.aim = Some(aim.normalize());
+    } else if controls.movement != Vec2::ZERO {
+        controls.aim = Some(controls.movement.normalize());
+    }
+
+    // Whichever device was touched last decides who moves the cursor
+    let gamepad_used = aim != Vec2::ZERO || controls.movement != Vec2::ZERO || controls.buttons.get_just_pressed().len() > 0;
+    let mouse_used = cursor_moved_er.iter().count() > 0 || mouse.get_just_pressed().len() > 0;
+    if mouse_used {
+        controls.active = false;
+    } else if gamepad_used {
+        controls.active = true;
+    }
+}
+
+// Put cursor in front of the player in aimed direction, so shooting and turning work as with mouse
+fn aim_with_gamepad(
+    controls: Res<GamepadControls>,
+    player: Query<&Transform, (With<Player>, Without<Cursor>)>,
+    camera: Query<&Transform, (With<MainCamera>, Without<Player>, Without<Cursor>)>,
+    mut cursor: Query<&mut Transform, (With<Cursor>, Without<Player>, Without<MainCamera>)>,
+) {
+    let (true, Some(aim)) = (controls.active, controls.aim) else {
+        return;
+    };
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    // Up on the stick is up on the screen
+    let (forward, right) = camera_basis(camera.single());
+    let direction = (forward * aim.y + right * aim.x).normalize_or_zero();
+    cursor.single_mut().translation = player.translation + direction * AIM_DISTANCE;
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy::render::mesh::shape as render_shape;
+
+use crate::enemies::Enemy;
+use crate::bosses::Boss;
+use crate::combat::despawn_dead;
+use crate::actions::Action;
+use crate::{GameState, Health, MainCamera};
+
+#[derive(Resource)]
+pub struct HealthBarSettings {
+    // Show bars of undamaged enemies too and never hide them
+    pub always_show: bool,
+    // Seconds without damage after which bar hides again
+    pub hide_after: f32,
+}
+
+impl Default for HealthBarSettings {
+    fn default() -> Self {
+        HealthBarSettings {
+            always_show: false,
+            hide_after: 3.0,
+        }
+    }
+}
+
+// Put on enemies and bosses once their bar is spawned
+#[derive(Component)]
+struct HealthBar {
+    bar: Entity,
+    fill: Entity,
+    fill_material: Handle<StandardMaterial>,
+    // Highest health seen, elites get theirs raised after spawn
+    max: i32,
+    last: i32,
+    since_damage: f32,
+    width: f32,
+    height: f32,
+}
+
+// Root of the bar, kept outside of owner hierarchy so it doesn't turn and scale with it
+#[derive(Component)]
+struct HealthBarOf(Entity);
+
+const BAR_HEIGHT: f32 = 0.12;
+
+pub struct HealthBarsPlugin;
+impl Plugin for HealthBarsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HealthBarSettings>()
+            .add_systems(
+                Update,
+                (
+                    // Bar is inserted on owner, so it must be alive
+                    attach_health_bars.before(despawn_dead),
+                    update_health_bars,
+                    despawn_orphaned_health_bars,
+                    toggle_health_bars,
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+fn attach_health_bars(
+    owners: Query<(Entity, &Health, Option<&Boss>), (Or<(With<Enemy>, With<Boss>)>, Without<HealthBar>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for (entity, health, boss) in owners.iter() {
+        let (width, height) = if boss.is_some() { (2.0, 3.2) } else { (1.0, 1.8) };
+        let quad = meshes.add(Mesh::from(render_shape::Quad::new(Vec2::new(width, BAR_HEIGHT))));
+        let fill_material = materials.add(StandardMaterial {
+            base_color: Color::GREEN,
+            unlit: true,
+            ..default()
+        });
+
+        let fill = commands
+            .spawn(PbrBundle {
+                mesh: quad.clone(),
+                material: fill_material.clone(),
+                transform: Transform::from_xyz(0.0, 0.0, 0.01),
+                ..default()
+            })
+            .id();
+        let bar = commands
+            .spawn(PbrBundle {
+                mesh: quad,
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(0.0, 0.0, 0.0, 0.6),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                visibility: Visibility::Hidden,
+                ..default()
+            })
+            .insert(HealthBarOf(entity))
+            .add_child(fill)
+            .id();
+
+        commands.entity(entity).insert(HealthBar {
+            bar,
+            fill,
+            fill_material,
+            max: health.0,
+            last: health.0,
+            // Fresh enemies start with hidden bar
+            since_damage: f32::MAX,
+            width,
+            height,
+        });
+    }
+}
+
+fn update_health_bars(
+    mut owners: Query<(&Transform, &Health, &mut HealthBar), Without<HealthBarOf>>,
+    mut bars: Query<(&mut Transform, &mut Visibility), (With<HealthBarOf>, Without<MainCamera>)>,
+    mut fills: Query<&mut Transform, (Without<HealthBarOf>, Without<HealthBar>, Without<MainCamera>)>,
+    camera: Query<&Transform, (With<MainCamera>, Without<HealthBarOf>, Without<HealthBar>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<HealthBarSettings>,
+    time: Res<Time>,
+) {
+    let camera_rotation = camera.single().rotation;
+    for (owner_transform, health, mut health_bar) in owners.iter_mut() {
+        let changed = health.0 != health_bar.last;
+        health_bar.max = health_bar.max.max(health.0);
+        if health.0 < health_bar.last {
+            health_bar.since_damage = 0.0;
+        } else {
+            health_bar.since_damage += time.delta_seconds();
+        }
+        health_bar.last = health.0;
+
+        // Fill only needs touching when health moves
+        if changed {
+            let fraction = (health.0 as f32 / health_bar.max as f32).clamp(0.0, 1.0);
+            if let Ok(mut fill_transform) = fills.get_mut(health_bar.fill) {
+                fill_transform.scale.x = fraction.max(0.001);
+                fill_transform.translation.x = -(1.0 - fraction) * health_bar.width / 2.0;
+            }
+            if let Some(material) = materials.get_mut(&health_bar.fill_material) {
+                material.base_color = Color::rgb(1.0 - fraction, fraction, 0.0);
+            }
+        }
+
+        let Ok((mut bar_transform, mut visibility)) = bars.get_mut(health_bar.bar) else {
+            continue;
+        };
+        let shown = settings.always_show || health_bar.since_damage < settings.hide_after;
+        *visibility = if shown { Visibility::Inherited } else { Visibility::Hidden };
+        if !shown {
+            continue;
+        }
+
+        // Billboard above the owner, always facing the camera
+        bar_transform.translation = owner_transform.translation + Vec3::Y * health_bar.height;
+        bar_transform.rotation = camera_rotation;
+    }
+}
+
+fn despawn_orphaned_health_bars(
+    bars: Query<(Entity, &HealthBarOf)>,
+    owners: Query<(), With<HealthBar>>,
+    mut commands: Commands,
+) {
+    for (bar, owner) in bars.iter() {
+        if !owners.contains(owner.0) {
+            commands.entity(bar).despawn_recursive();
+        }
+    }
+}
+
+fn toggle_health_bars(actions: Res<Input<Action>>, mut settings: ResMut<HealthBarSettings>) {
+    if actions.just_pressed(Action::ToggleHealthBars) {
+        settings.always_show = !settings.always_show;
+    }
+}
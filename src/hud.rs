@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+
+use crate::player::{Ammo, Inventory, Player, Weapon};
+use crate::enemies::{Enemy, Wave};
+use crate::bosses::Boss;
+use crate::weapons::{Arsenal, WeaponDefinition, WeaponsAsset};
+use crate::{Game, GameState, Health, MaxHealth};
+
+// Time since the run started
+#[derive(Resource, Default)]
+pub struct RunTime(pub f32);
+
+#[derive(Component)]
+struct HealthFill;
+
+#[derive(Component)]
+struct HealthLabel;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct RunTimeText;
+
+#[derive(Component)]
+struct EnemiesText;
+
+#[derive(Component)]
+struct WeaponIcon;
+
+#[derive(Component)]
+struct WeaponIconLabel;
+
+#[derive(Component)]
+struct WeaponNameText;
+
+#[derive(Component)]
+struct AmmoText;
+
+#[derive(Component)]
+struct WeaponBarText;
+
+const PANEL_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const HIGHLIGHT_COLOR: Color = Color::rgb(0.9, 0.8, 0.2);
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTime>()
+            .add_systems(Startup, setup_hud)
+            .add_systems(
+                Update,
+                (
+                    tick_run_time,
+                    show_health,
+                    show_score,
+                    show_wave,
+                    show_run_time.after(tick_run_time),
+                    show_enemies_remaining,
+                    show_weapon,
+                    show_weapon_bar,
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+fn text(value: &str, size: f32, color: Color, font: &Handle<Font>) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font: font.clone(),
+            font_size: size,
+            color,
+        },
+    )
+}
+
+fn setup_hud(asset_server: Res<AssetServer>, mut commands: Commands) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    // Health bar and score in the top left corner
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                left: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|panel| {
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(220.0),
+                        height: Val::Px(24.0),
+                        ..default()
+                    },
+                    background_color: PANEL_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.7, 0.0, 0.0).into(),
+                        ..default()
+                    })
+                    .insert(HealthFill);
+                    bar.spawn(text("", 20.0, Color::WHITE, &font).with_style(Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(6.0),
+                        ..default()
+                    }))
+                    .insert(HealthLabel);
+                });
+            panel.spawn(text("Score: 0", 30.0, Color::WHITE, &font)).insert(ScoreText);
+        });
+
+    // Wave, run time and enemies left across the top
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(24.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn(text("Wave 1", 30.0, HIGHLIGHT_COLOR, &font)).insert(WaveText);
+            row.spawn(text("00:00", 30.0, Color::WHITE, &font)).insert(RunTimeText);
+            row.spawn(text("", 30.0, Color::WHITE, &font)).insert(EnemiesText);
+        });
+
+    // Current weapon with its ammo, unlocked weapons under it
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|panel| {
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(44.0),
+                            height: Val::Px(44.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::GRAY.into(),
+                        ..default()
+                    })
+                    .insert(WeaponIcon)
+                    .with_children(|icon| {
+                        icon.spawn(text("", 22.0, Color::BLACK, &font)).insert(WeaponIconLabel);
+                    });
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|column| {
+                        column.spawn(text("", 26.0, Color::WHITE, &font)).insert(WeaponNameText);
+                        column.spawn(text("", 22.0, HIGHLIGHT_COLOR, &font)).insert(AmmoText);
+                    });
+                });
+            panel.spawn(text("", 22.0, Color::GRAY, &font)).insert(WeaponBarText);
+        });
+}
+
+// Short label for the weapon icon made of initials, like "GL" for grenade launcher
+fn weapon_initials(definition: &WeaponDefinition) -> String {
+    definition
+        .name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .collect()
+}
+
+fn tick_run_time(mut run_time: ResMut<RunTime>, time: Res<Time>) {
+    run_time.0 += time.delta_seconds();
+}
+
+fn show_health(
+    player: Query<(&Health, &MaxHealth), (With<Player>, Or<(Changed<Health>, Changed<MaxHealth>)>)>,
+    mut fill: Query<&mut Style, With<HealthFill>>,
+    mut label: Query<&mut Text, With<HealthLabel>>,
+) {
+    let Ok((health, max_health)) = player.get_single() else {
+        return;
+    };
+    let fraction = (health.0 as f32 / max_health.0 as f32).clamp(0.0, 1.0);
+    fill.single_mut().width = Val::Percent(fraction * 100.0);
+    label.single_mut().sections[0].value = format!("{} / {}", health.0.max(0), max_health.0);
+}
+
+fn show_score(game: Res<Game>, mut text: Query<&mut Text, With<ScoreText>>) {
+    if game.is_changed() {
+        text.single_mut().sections[0].value = format!("Score: {}", game.score);
+    }
+}
+
+fn show_wave(wave: Res<Wave>, mut text: Query<&mut Text, With<WaveText>>) {
+    if wave.is_changed() {
+        text.single_mut().sections[0].value = format!("Wave {}", wave.0);
+    }
+}
+
+fn show_run_time(
+    run_time: Res<RunTime>,
+    mut shown_seconds: Local<Option<u32>>,
+    mut text: Query<&mut Text, With<RunTimeText>>,
+) {
+    let seconds = run_time.0 as u32;
+    if *shown_seconds == Some(seconds) {
+        return;
+    }
+    *shown_seconds = Some(seconds);
+    text.single_mut().sections[0].value = format!("{:02}:{:02}", seconds / 60, seconds % 60);
+}
+
+fn show_enemies_remaining(
+    enemies: Query<(), Or<(With<Enemy>, With<Boss>)>>,
+    mut shown_count: Local<Option<usize>>,
+    mut text: Query<&mut Text, With<EnemiesText>>,
+) {
+    let count = enemies.iter().count();
+    if *shown_count == Some(count) {
+        return;
+    }
+    *shown_count = Some(count);
+    text.single_mut().sections[0].value = format!("Enemies: {}", count);
+}
+
+fn show_weapon(
+    player: Query<(Ref<Weapon>, Ref<Ammo>), With<Player>>,
+    arsenal: Res<Arsenal>,
+    weapon_assets: Res<Assets<WeaponsAsset>>,
+    mut icon: Query<&mut BackgroundColor, With<WeaponIcon>>,
+    mut texts: ParamSet<(
+        Query<&mut Text, With<WeaponIconLabel>>,
+        Query<&mut Text, With<WeaponNameText>>,
+        Query<&mut Text, With<AmmoText>>,
+    )>,
+) {
+    let (weapon, ammo) = player.single();
+    if !weapon.is_changed() && !ammo.is_changed() && !weapon_assets.is_changed() {
+        return;
+    }
+    let Some(definitions) = arsenal.definitions(&weapon_assets) else {
+        return;
+    };
+    let (Some(definition), Some((magazine, reserve))) = (definitions.get(weapon.index()), ammo.rounds(weapon.index())) else {
+        return;
+    };
+
+    *icon.single_mut() = definition.damage_type.color().into();
+    texts.p0().single_mut().sections[0].value = weapon_initials(definition);
+    texts.p1().single_mut().sections[0].value = definition.name.clone();
+    let reserve = reserve.map_or("inf".to_string(), |reserve| reserve.to_string());
+    let status = if ammo.reloading() { "  Reloading..." } else { "" };
+    texts.p2().single_mut().sections[0].value = format!("{} / {}{}", magazine, reserve, status);
+}
+
+fn show_weapon_bar(
+    player: Query<(Ref<Weapon>, Ref<Inventory>), With<Player>>,
+    arsenal: Res<Arsenal>,
+    weapon_assets: Res<Assets<WeaponsAsset>>,
+    mut text_query: Query<&mut Text, With<WeaponBarText>>,
+) {
+    let (weapon, inventory) = player.single();
+    if !weapon.is_changed() && !inventory.is_changed() && !weapon_assets.is_changed() {
+        return;
+    }
+    let Some(definitions) = arsenal.definitions(&weapon_assets) else {
+        return;
+    };
+    let mut text = text_query.single_mut();
+    let style = text.sections[0].style.clone();
+
+    text.sections = inventory
+        .unlocked()
+        .iter()
+        .filter_map(|&index| definitions.get(index).map(|definition| (index, definition)))
+        .map(|(index, definition)| {
+            let color = if index == weapon.index() { HIGHLIGHT_COLOR } else { Color::GRAY };
+            TextSection::new(
+                format!("[{}] {}   ", index + 1, definition.name),
+                TextStyle { color, ..style.clone() },
+            )
+        })
+        .collect();
+    if text.sections.is_empty() {
+        text.sections.push(TextSection::new("", style));
+    }
+}
@@ -9,6 +9,31 @@ LONGTERM:
 - Add levels with different layout, platforms etc.
 
 DONE:
+- Map input to rebindable actions, pause menu (P) lets you change bindings, saved to bindings.ron
+- Add twin-stick gamepad controls (sticks move and aim, triggers fire and melee, bumpers switch weapons)
+- Aim at a plane at player height instead of any collider under the mouse, show aim reticle
+- Fade walls and other level geometry that hides the player from the camera
+- Add screen shake and hit-stop on heavy hits, both can be turned off in the pause menu (Pause action)
+- Add camera rig with zoom (+/- or ctrl + wheel), Q/E rotation, smoothing and look-ahead
+- Add radar with enemies, bosses, pickups and missiles, plus arrows to off-screen bosses
+- Add HUD with health bar, weapon icon and ammo, wave, run time, enemies left and score
+- Show health bars above damaged enemies and bosses (H to always show them)
+- Make floating text follow its world position, rise, fade, merge quick hits and scale with damage
+- Add damage types (kinetic, explosive, fire, energy), armor, resistances and critical hits
+- Add status effects (burn, poison, slow, freeze, stun) with stacking, immunities and indicators
+- Add timed power-ups (double damage, rapid fire, speed, shield, multi-shot) with HUD icons
+- Add pickup kinds with loot tables, several pickups at once, blinking before expiry and enemy drops
+- Animate characters (idle, run, attack, hit, death) with clips built from model rest pose, exported clips with the same names replace them
+- Make melee hit in an arc towards cursor with 3-hit combo, knockback and attack clip in sync (built arm swing until models ship one)
+- Add grenade launcher (area damage), flamethrower (burning) and piercing railgun
+- Unlock weapons with pickups, select them with number keys or mouse wheel, show weapon bar
+- Move weapon stats to data file (assets/data/arsenal.weapons.ron) with fire modes
+- Add magazines, reserve ammo, reloading and ammo pickups for player weapons
+- Add elite enemies with random affixes (armored, fast, splitting, shielded, vampiric, explosive)
+- Make enemies and bosses shoot only when they see player (view cones, line of sight, memory)
+- Add state machine AI, ranged enemies keep distance, strafe, retreat and flee
+- Make enemies spread out and flank player instead of piling up (crowd steering)
+- Make chasing enemies and bosses find path around obstacles (A* on navigation grid)
 - Make all velocity depend on time.delta_seconds()
 - Add text information when attacks happens (floating text)
 - Add boss that shoots autonomous missiles
@@ -38,8 +63,6 @@ DONE:
 - Add jump
 */
 
-use rand::Rng;
-
 use bevy::render::mesh::shape as render_shape;
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_rapier3d::prelude::*;
@@ -47,12 +70,37 @@ use bevy_rapier3d::prelude::*;
 mod player;
 mod enemies;
 mod bosses;
+mod navigation;
+mod steering;
+mod ai;
+mod perception;
+mod combat;
+mod elites;
+mod weapons;
+mod melee;
+mod animation;
+mod pickups;
+mod powerups;
+mod status;
+mod floating_text;
+mod health_bars;
+mod hud;
+mod minimap;
+mod camera;
+mod settings;
+mod feedback;
+mod occlusion;
+mod gamepad;
+mod actions;
 use player::Player;
+use camera::CameraRig;
+use gamepad::GamepadControls;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States, SystemSet)]
 enum GameState {
     #[default]
     Playing,
+    Paused,
 }
 
 #[derive(Component)]
@@ -62,46 +110,67 @@ struct MainCamera;
 struct Cursor;
 
 #[derive(Component)]
-struct BonusComponent;
+pub struct Level;
 
 #[derive(Component)]
 struct Health(i32);
 
+// Health can't be raised above this
 #[derive(Component)]
-struct HealthText;
-
-#[derive(Component)]
-pub struct FloatingText {
-    pub offset: f32,
-    pub time_to_live: f32,
-}
+struct MaxHealth(i32);
 
 #[derive(Event)]
 pub struct FloatingTextEvent {
     pub translation: Vec3,
     pub text: String,
     pub color: Color,
-}
-
-#[derive(Default)]
-struct Bonus {
-    entity: Option<Entity>,
-    x: f32,
-    z: f32,
-    handle: Handle<Scene>,
+    pub size: f32,
 }
 
 #[derive(Resource, Default)]
 struct Game {
-    bonus: Bonus,
     player: Option<Entity>,
+    score: u32,
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
 }
 
-#[derive(Resource)]
-struct BonusSpawnTimer(Timer);
+impl Difficulty {
+    pub fn multiplier(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    // Settings menu cycles through difficulties
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
 
 const DEFAULT_PLAYER_POS: [f32; 3] = [0.0, 1.0, 0.0];
 const DEFAULT_CAMERA_POS: [f32; 3] = [-7.0, 10.0, 0.0];
+pub const GROUND_SIZE: f32 = 20.0;
 
 fn main() {
     App::new()
@@ -111,12 +180,32 @@ fn main() {
         .add_plugins(player::PlayerPlugin)
         .add_plugins(enemies::EnemiesPlugin)
         .add_plugins(bosses::BossesPlugin)
+        .add_plugins(navigation::NavigationPlugin)
+        .add_plugins(ai::AiPlugin)
+        .add_plugins(perception::PerceptionPlugin)
+        .add_plugins(combat::CombatPlugin)
+        .add_plugins(elites::ElitesPlugin)
+        .add_plugins(weapons::WeaponsPlugin)
+        .add_plugins(melee::MeleePlugin)
+        .add_plugins(animation::CharacterAnimationPlugin)
+        .add_plugins(pickups::PickupsPlugin)
+        .add_plugins(powerups::PowerUpsPlugin)
+        .add_plugins(status::StatusPlugin)
+        .add_plugins(floating_text::FloatingTextPlugin)
+        .add_plugins(health_bars::HealthBarsPlugin)
+        .add_plugins(hud::HudPlugin)
+        .add_plugins(minimap::MinimapPlugin)
+        .add_plugins(camera::CameraRigPlugin)
+        .add_plugins(settings::SettingsPlugin)
+        .add_plugins(feedback::FeedbackPlugin)
+        .add_plugins(occlusion::OcclusionPlugin)
+        .add_plugins(gamepad::GamepadControlsPlugin)
+        .add_plugins(actions::ActionsPlugin)
+        .init_resource::<Difficulty>()
         .init_resource::<Game>()
-        .insert_resource(BonusSpawnTimer(Timer::from_seconds(
-            5.0,
-            TimerMode::Repeating,
-        )))
         .add_state::<GameState>()
+        // Gameplay systems only run while not paused
+        .configure_set(Update, GameState::Playing.run_if(in_state(GameState::Playing)))
         .add_systems(
             Startup,
         (
@@ -129,96 +218,31 @@ fn main() {
             Update,
             (
                 move_cursor,
-                move_camera,
-                spawn_bonus,
-                show_health,
-                spawn_bonus,
-                get_bonus,
-                create_floating_text
             )
             .in_set(GameState::Playing),
         )
-        .add_event::<FloatingTextEvent>()
         .add_systems(Update, bevy::window::close_on_esc)
         .run();
 }
 
-fn create_floating_text(
+fn setup(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
     mut commands: Commands,
-    camera: Query<(&Camera, &mut GlobalTransform), (With<MainCamera>, Without<Player>)>,
-    mut attack_text: Query<(Entity, &mut FloatingText)>,
-    asset_server: Res<AssetServer>,
-    time: Res<Time>,
-    mut floating_text_event_reader: EventReader<FloatingTextEvent>,
 ) {
-    for event in floating_text_event_reader.iter() {
-        for (camera, global_transform) in camera.iter() {
-            match camera.world_to_viewport(global_transform, event.translation) {
-                Some(coords) => {
-                    commands.spawn(
-                        TextBundle::from_section(
-                            event.text.clone(),
-                            TextStyle {
-                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                font_size: 40.0,
-                                color: event.color,
-                            },
-                        )
-                        .with_style(Style {
-                            position_type: PositionType::Absolute,
-                            bottom: Val::Px(coords.y),
-                            left: Val::Px(coords.x),
-                            ..default()
-                        }),
-                    )
-                    .insert(FloatingText{offset: 0.0, time_to_live: 1.0});
-                }
-                None => {}
-            }
-        }
-    }
-
-    // Calculate when floating text should be hidden 
-    for (entity, mut attack_text_struct) in attack_text.iter_mut() {
-        attack_text_struct.offset += time.delta_seconds();
-
-        if attack_text_struct.offset > attack_text_struct.time_to_live {
-            commands.entity(entity).despawn_recursive();
-        }
-    }
-}
-
-fn setup(asset_server: Res<AssetServer>, mut game: ResMut<Game>, mut commands: Commands) {
-    // load the scene for the bonus
-    game.bonus.handle = asset_server.load("models/pumpkin.glb#Scene0");
-
-    // scoreboard
-    commands.spawn(
-        TextBundle::from_section(
-            "Score:",
-            TextStyle {
-                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                font_size: 40.0,
-                color: Color::rgb(0.7, 0.0, 0.0),
-            },
-        )
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            top: Val::Px(5.0),
-            left: Val::Px(5.0),
-            ..default()
-        }),
-    ).insert(HealthText);
-
-    // Setup cursor
+    // Setup cursor, a flat ring showing where player aims
     commands
-        .spawn(SceneBundle {
-            transform: Transform {
-                translation: Vec3::ZERO,
-                scale: Vec3::new(2.0, 2.0, 2.0),
+        .spawn(PbrBundle {
+            mesh: meshes.add(Mesh::from(render_shape::Torus {
+                radius: 0.3,
+                ring_radius: 0.03,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(1.0, 0.3, 0.2),
+                unlit: true,
                 ..default()
-            },
-            // scene: game.bonus.handle.clone(),
+            }),
             ..default()
         })
         .insert(Cursor);
@@ -231,7 +255,11 @@ fn setup_camera(mut commands: Commands) {
                 .looking_at(Vec3::from(DEFAULT_PLAYER_POS), Vec3::Y), // focus rotation of camera on player
             ..default()
         })
-        .insert(MainCamera);
+        .insert(MainCamera)
+        .insert(CameraRig::new(
+            Vec3::from(DEFAULT_CAMERA_POS) - Vec3::from(DEFAULT_PLAYER_POS),
+            Vec3::from(DEFAULT_PLAYER_POS),
+        ));
 }
 
 fn setup_light(mut commands: Commands) {
@@ -250,75 +278,39 @@ fn setup_light(mut commands: Commands) {
     });
 }
 
-fn move_camera(
-    mut camera_transform: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
-    mut player_transform: Query<&Transform, (With<Player>, Without<MainCamera>)>,
-) {
-    let player_pos = player_transform.single_mut().translation;
-
-    let camera_distance: Vec3 = Vec3::from(DEFAULT_CAMERA_POS) - Vec3::from(DEFAULT_PLAYER_POS);
-    let new_camera_pos = player_pos + camera_distance;
-
-    // Interpolated camera movement
-    camera_transform.single_mut().translation = camera_transform
-        .single_mut()
-        .translation
-        .lerp(new_camera_pos, 0.2);
-}
-
+// Aim at a horizontal plane at player height, so enemies or the player under
+// the mouse don't pull the aim point up or towards the camera
 fn move_cursor(
-    rapier_context: Res<RapierContext>,
     primary_query: Query<&Window, With<PrimaryWindow>>,
     q_camera: Query<(&Camera, &GlobalTransform), (With<MainCamera>, Without<Player>)>,
+    player: Query<&Transform, (With<Player>, Without<Cursor>)>,
     mut cursor_transform: Query<&mut Transform, With<Cursor>>,
+    gamepad: Res<GamepadControls>,
 ) {
+    // Right stick moves the cursor while gamepad is in use
+    if gamepad.active {
+        return;
+    }
     let (camera, camera_transform) = q_camera.single();
     let Ok(primary) = primary_query.get_single() else {
         return;
     };
-    if let Some(screen_pos) = primary.cursor_position() {
-        let world_ray = camera
-            .viewport_to_world(camera_transform, screen_pos)
-            .unwrap();
-
-        let ray_pos = world_ray.origin;
-        let ray_dir = world_ray.direction;
-        let max_toi = 100.0;
-        let solid = true;
-        let filter = QueryFilter { ..default() };
-        if let Some((_entity, intersection)) =
-            rapier_context.cast_ray_and_get_normal(ray_pos, ray_dir, max_toi, solid, filter)
-        {
-            let hit_point = intersection.point;
-            cursor_transform.single_mut().translation = hit_point;
-        }
-    }
-}
-
-fn get_bonus(
-    mut player: Query<(Entity, &mut Health), With<Player>>,
-    bonus: Query<(Entity, &Transform), With<BonusComponent>>,
-    mut game: ResMut<Game>,
-    rapier_context: Res<RapierContext>,
-    mut commands: Commands,
-) {
-    for (bonus_entity, bonus_transform) in bonus.iter() {
-        let shape = Collider::ball(0.5);
-        let shape_pos = bonus_transform.translation;
-        let shape_rot = bonus_transform.rotation;
-        let filter = QueryFilter::default();
-
-        rapier_context.intersections_with_shape(shape_pos, shape_rot, &shape, filter, |entity| {
-            if entity == player.single().0 {
-                commands.entity(bonus_entity).despawn_recursive();
-                game.bonus.entity = None;
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let Some(world_ray) = primary
+        .cursor_position()
+        .and_then(|screen_pos| camera.viewport_to_world(camera_transform, screen_pos))
+    else {
+        return;
+    };
 
-                // Add player health
-                player.single_mut().1 .0 += 1;
-            }
-            true
-        });
-    }
+    // Ray pointing above the horizon never reaches the plane
+    let aim_height = player.translation.y;
+    let Some(distance) = world_ray.intersect_plane(Vec3::new(0.0, aim_height, 0.0), Vec3::Y) else {
+        return;
+    };
+    cursor_transform.single_mut().translation = world_ray.get_point(distance);
 }
 
 fn spawn_level(
@@ -326,96 +318,49 @@ fn spawn_level(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut commands: Commands,
 ) {
-    let ground_size = 20.0;
     let ground_height = 0.1;
 
     // Spawn ground
     commands
         .spawn(PbrBundle {
             mesh: meshes.add(Mesh::from(render_shape::Box::new(
-                ground_size,
+                GROUND_SIZE,
                 ground_height,
-                ground_size,
+                GROUND_SIZE,
             ))),
             material: materials.add(Color::DARK_GREEN.into()),
             transform: Transform::from_xyz(0.0, -ground_height, 0.0),
             ..default()
         })
         .insert(Collider::cuboid(
-            ground_size / 2.0,
+            GROUND_SIZE / 2.0,
             ground_height / 2.0,
-            ground_size / 2.0,
+            GROUND_SIZE / 2.0,
         ))
         .insert(Transform::from_xyz(0.0, -ground_height, 0.0))
-        .insert(GlobalTransform::default());
-}
+        .insert(GlobalTransform::default())
+        .insert(Level);
 
-// despawn the bonus if there is one, then spawn a new one at a random location
-fn spawn_bonus(
-    time: Res<Time>,
-    mut timer: ResMut<BonusSpawnTimer>,
-    mut commands: Commands,
-    mut game: ResMut<Game>,
-    mut player_transform: Query<&Transform, With<Player>>,
-) {
-    // Make sure we wait enough time before spawning the next bonus
-    if !timer.0.tick(time.delta()).finished() {
-        return;
-    }
-
-    if let Some(entity) = game.bonus.entity {
-        commands.entity(entity).despawn_recursive();
-        game.bonus.entity = None;
-    }
-
-    // Ensure bonus doesn't spawn on the player
-    loop {
-        game.bonus.x = rand::thread_rng().gen_range(-7.0..7.0);
-        game.bonus.z = rand::thread_rng().gen_range(-7.0..7.0);
-        let player_pos = Vec2::new(
-            player_transform.single_mut().translation[0],
-            player_transform.single_mut().translation[2],
-        );
-        let bonus_pos = Vec2::new(game.bonus.x, game.bonus.z);
-        if player_pos.distance(bonus_pos) > 2.0 {
-            break;
-        }
-    }
-    game.bonus.entity = Some(
-        commands
-            .spawn(SceneBundle {
-                transform: Transform {
-                    translation: Vec3::new(game.bonus.x, 0.5, game.bonus.z),
-                    scale: Vec3::new(2.0, 2.0, 2.0),
-                    ..default()
-                },
-                scene: game.bonus.handle.clone(),
-                ..default()
-            })
-            .with_children(|children| {
-                children.spawn(PointLightBundle {
-                    point_light: PointLight {
-                        color: Color::rgb(1.0, 1.0, 0.0),
-                        intensity: 1000.0,
-                        range: 10.0,
-                        ..default()
-                    },
-                    transform: Transform::from_xyz(0.0, 2.0, 0.0),
-                    ..default()
-                });
-            })
-            .insert(Velocity::zero())
-            .insert(BonusComponent)
-            .id(),
-    );
+    // Spawn walls for enemies to walk around, kept inside the ring enemies spawn on
+    spawn_wall(Vec3::new(3.5, 0.75, -2.0), Vec3::new(1.0, 1.5, 4.0), &mut meshes, &mut materials, &mut commands);
+    spawn_wall(Vec3::new(-3.0, 0.75, 3.5), Vec3::new(4.0, 1.5, 1.0), &mut meshes, &mut materials, &mut commands);
+    spawn_wall(Vec3::new(-3.0, 0.75, -3.5), Vec3::new(1.0, 1.5, 3.0), &mut meshes, &mut materials, &mut commands);
 }
 
-// Update the health displayed during the game
-fn show_health(
-    mut text_query: Query<&mut Text, With<HealthText>>,
-    mut health_query: Query<&Health, With<Player>>,
+fn spawn_wall(
+    translation: Vec3,
+    size: Vec3,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    commands: &mut Commands,
 ) {
-    let mut text = text_query.single_mut();
-    let health = health_query.single_mut();
-    text.sections[0].value = format!("Health: {}", health.0);
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(Mesh::from(render_shape::Box::new(size.x, size.y, size.z))),
+            material: materials.add(Color::GRAY.into()),
+            transform: Transform::from_translation(translation),
+            ..default()
+        })
+        .insert(Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0))
+        .insert(Level);
 }
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::Player;
+use crate::animation::{AnimationController, AnimationKind};
+use crate::combat::{apply_damage, DamageEvent, DamageType, Knockback};
+use crate::status::StatusEffects;
+use crate::actions::Action;
+use crate::{GameState, Health};
+
+struct Swing {
+    // Whole angle in front of player that gets hit, in radians
+    arc: f32,
+    range: f32,
+    damage: i32,
+    crit_chance: f32,
+    knockback: f32,
+    duration: f32,
+}
+
+// Every swing of the chain hits wider and harder than previous one
+const COMBO: [Swing; 3] = [
+    Swing {
+        arc: 1.6,
+        range: 1.6,
+        damage: 10,
+        crit_chance: 0.05,
+        knockback: 3.0,
+        duration: 0.25,
+    },
+    Swing {
+        arc: 1.6,
+        range: 1.6,
+        damage: 10,
+        crit_chance: 0.05,
+        knockback: 3.0,
+        duration: 0.25,
+    },
+    Swing {
+        arc: 2.6,
+        range: 2.0,
+        damage: 20,
+        crit_chance: 0.25,
+        knockback: 7.0,
+        duration: 0.4,
+    },
+];
+
+// Time after swing ends in which next click continues the chain
+const COMBO_WINDOW: f32 = 0.5;
+// Pause after the last swing of the chain
+const COMBO_COOLDOWN: f32 = 0.6;
+
+#[derive(Component, Default)]
+pub struct MeleeCombo {
+    // Swings done in current chain
+    chain: usize,
+    swing_elapsed: Option<f32>,
+    since_swing: f32,
+    // Click during swing continues the chain right after it
+    queued: bool,
+    cooldown: f32,
+}
+
+pub struct MeleePlugin;
+impl Plugin for MeleePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                // Knockback is inserted on targets, so they must be alive
+                player_melee_attack.before(apply_damage),
+            )
+            .in_set(GameState::Playing),
+        );
+    }
+}
+
+impl MeleeCombo {
+    fn current_swing(&self) -> &'static Swing {
+        &COMBO[self.chain.saturating_sub(1)]
+    }
+}
+
+fn player_melee_attack(
+    mut player: Query<(Entity, &Transform, &mut MeleeCombo, &mut AnimationController, &StatusEffects), With<Player>>,
+    targets: Query<&Transform, (With<Health>, Without<Player>)>,
+    rapier_context: Res<RapierContext>,
+    actions: Res<Input<Action>>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let (player_entity, player_transform, mut combo, mut animation, status_effects) = player.single_mut();
+    combo.cooldown -= time.delta_seconds();
+
+    let clicked = actions.just_pressed(Action::Melee);
+    let start_swing;
+
+    if let Some(elapsed) = combo.swing_elapsed {
+        if clicked && combo.chain < COMBO.len() {
+            combo.queued = true;
+        }
+
+        let elapsed = elapsed + time.delta_seconds();
+        if elapsed < combo.current_swing().duration {
+            combo.swing_elapsed = Some(elapsed);
+            return;
+        }
+
+        // Swing finished, either continue chain or wait for next click
+        combo.swing_elapsed = None;
+        combo.since_swing = 0.0;
+        if combo.chain == COMBO.len() {
+            combo.chain = 0;
+            combo.cooldown = COMBO_COOLDOWN;
+        }
+        start_swing = std::mem::take(&mut combo.queued);
+    } else {
+        combo.since_swing += time.delta_seconds();
+        if combo.since_swing > COMBO_WINDOW {
+            combo.chain = 0;
+        }
+        start_swing = clicked && combo.cooldown <= 0.0 && status_effects.can_act();
+    }
+
+    if !start_swing {
+        return;
+    }
+    combo.chain += 1;
+    combo.swing_elapsed = Some(0.0);
+    let swing = combo.current_swing();
+    animation.play_once(AnimationKind::Attack, swing.duration);
+
+    // Hit everything inside the arc in front of player, facing follows cursor
+    let forward = player_transform.rotation * Vec3::X;
+    let shape = Collider::ball(swing.range);
+    let filter = QueryFilter::default().exclude_collider(player_entity);
+    rapier_context.intersections_with_shape(player_transform.translation, Quat::IDENTITY, &shape, filter, |entity| {
+        let Ok(target_transform) = targets.get(entity) else {
+            return true;
+        };
+        let offset = target_transform.translation - player_transform.translation;
+        let flat_offset = Vec3::new(offset.x, 0.0, offset.z);
+        if flat_offset.length() > 0.0 && flat_offset.angle_between(forward) > swing.arc / 2.0 {
+            return true;
+        }
+
+        damage_event_writer.send(DamageEvent {
+            target: entity,
+            source: Some(player_entity),
+            amount: swing.damage,
+            damage_type: DamageType::Kinetic,
+            crit_chance: swing.crit_chance,
+            over_time: false,
+        });
+        commands
+            .entity(entity)
+            .insert(Knockback::new(flat_offset.normalize_or_zero() * swing.knockback));
+        true
+    });
+}
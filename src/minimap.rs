@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::player::Player;
+use crate::enemies::Enemy;
+use crate::bosses::{Boss, BossMissile};
+use crate::pickups::Pickup;
+use crate::camera::camera_basis;
+use crate::{GameState, MainCamera};
+
+#[derive(Component)]
+struct Radar;
+
+#[derive(Component)]
+struct Blip;
+
+#[derive(Component)]
+struct BossArrow;
+
+// Size of the radar in pixels and how far around the player it reaches
+const RADAR_SIZE: f32 = 160.0;
+const RADAR_RANGE: f32 = 14.0;
+// Distance of boss arrows from the window edge
+const ARROW_MARGIN: f32 = 30.0;
+const ARROW_SIZE: f32 = 40.0;
+const BOSS_COLOR: Color = Color::PURPLE;
+
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_radar).add_systems(
+            Update,
+            (update_radar, show_boss_arrows).in_set(GameState::Playing),
+        );
+    }
+}
+
+fn setup_radar(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                right: Val::Px(5.0),
+                width: Val::Px(RADAR_SIZE),
+                height: Val::Px(RADAR_SIZE),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..default()
+        })
+        .insert(Radar)
+        .with_children(|radar| {
+            // Player always sits in the middle
+            radar.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(RADAR_SIZE / 2.0 - 4.0),
+                    top: Val::Px(RADAR_SIZE / 2.0 - 4.0),
+                    width: Val::Px(8.0),
+                    height: Val::Px(8.0),
+                    ..default()
+                },
+                background_color: Color::WHITE.into(),
+                ..default()
+            });
+        });
+}
+
+// Offset from the player turned so that up on the radar is up on the screen
+fn radar_offset(offset: Vec3, camera_transform: &Transform) -> Vec2 {
+    let (forward, right) = camera_basis(camera_transform);
+    Vec2::new(offset.dot(right), offset.dot(forward))
+}
+
+fn update_radar(
+    player: Query<&Transform, With<Player>>,
+    camera: Query<&Transform, With<MainCamera>>,
+    tracked: Query<
+        (Entity, &Transform, Option<&Boss>, Option<&Pickup>, Option<&BossMissile>),
+        Or<(With<Enemy>, With<Boss>, With<Pickup>, With<BossMissile>)>,
+    >,
+    radar: Query<Entity, With<Radar>>,
+    mut blip_nodes: Query<(&mut Style, &mut Visibility), With<Blip>>,
+    // Radar blip of every tracked entity
+    mut blips: Local<HashMap<Entity, Entity>>,
+    mut commands: Commands,
+) {
+    let player_position = player.single().translation;
+    let camera_transform = camera.single();
+    let radar = radar.single();
+
+    for (entity, transform, boss, pickup, missile) in tracked.iter() {
+        let (color, size) = match (boss, pickup, missile) {
+            (Some(_), _, _) => (BOSS_COLOR, 12.0),
+            (_, Some(pickup), _) => (pickup.kind().color(), 7.0),
+            (_, _, Some(_)) => (Color::ORANGE, 5.0),
+            _ => (Color::RED, 7.0),
+        };
+
+        let mut offset = radar_offset(transform.translation - player_position, camera_transform);
+        // Bosses stick to the rim when out of range, everything else disappears
+        let in_range = offset.length() <= RADAR_RANGE;
+        if !in_range && boss.is_some() {
+            offset = offset.normalize() * RADAR_RANGE;
+        }
+        let shown = in_range || boss.is_some();
+
+        let scale = (RADAR_SIZE / 2.0) / RADAR_RANGE;
+        let left = Val::Px(RADAR_SIZE / 2.0 + offset.x * scale - size / 2.0);
+        let top = Val::Px(RADAR_SIZE / 2.0 - offset.y * scale - size / 2.0);
+        let visibility = if shown { Visibility::Inherited } else { Visibility::Hidden };
+
+        match blips.get(&entity).and_then(|&blip| blip_nodes.get_mut(blip).ok()) {
+            Some((mut style, mut blip_visibility)) => {
+                style.left = left;
+                style.top = top;
+                *blip_visibility = visibility;
+            }
+            None => {
+                let blip = commands
+                    .spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            left,
+                            top,
+                            width: Val::Px(size),
+                            height: Val::Px(size),
+                            ..default()
+                        },
+                        background_color: color.into(),
+                        visibility,
+                        ..default()
+                    })
+                    .insert(Blip)
+                    .id();
+                commands.entity(radar).add_child(blip);
+                blips.insert(entity, blip);
+            }
+        }
+    }
+
+    // Drop blips of everything that is gone
+    blips.retain(|&entity, &mut blip| {
+        let alive = tracked.contains(entity);
+        if !alive {
+            commands.entity(blip).despawn_recursive();
+        }
+        alive
+    });
+}
+
+// Arrow at the window edge pointing towards every boss that is off screen
+fn show_boss_arrows(
+    bosses: Query<(Entity, &Transform), With<Boss>>,
+    player: Query<&Transform, With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform, &Transform), With<MainCamera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut arrow_nodes: Query<(&mut Style, &mut Text, &mut Visibility), With<BossArrow>>,
+    mut arrows: Local<HashMap<Entity, Entity>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    const ARROWS: [&str; 8] = ["→", "↘", "↓", "↙", "←", "↖", "↑", "↗"];
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    let (camera, camera_global_transform, camera_transform) = camera.single();
+    let player_position = player.single().translation;
+
+    for (boss, boss_transform) in bosses.iter() {
+        let on_screen = camera
+            .world_to_viewport(camera_global_transform, boss_transform.translation)
+            .map_or(false, |position| {
+                position.cmpge(Vec2::ZERO).all() && position.cmple(window_size).all()
+            });
+
+        // Point from the middle of the window, screen y goes down
+        let offset = radar_offset(boss_transform.translation - player_position, camera_transform);
+        let direction = Vec2::new(offset.x, -offset.y).normalize_or_zero();
+        if direction == Vec2::ZERO {
+            continue;
+        }
+        let half = window_size / 2.0 - Vec2::splat(ARROW_MARGIN);
+        let reach = (half.x / direction.x.abs()).min(half.y / direction.y.abs());
+        let position = window_size / 2.0 + direction * reach - Vec2::splat(ARROW_SIZE / 2.0);
+        let octant = (direction.y.atan2(direction.x) / FRAC_PI_4).round() as i32;
+        let glyph = ARROWS[octant.rem_euclid(8) as usize];
+        let visibility = if on_screen { Visibility::Hidden } else { Visibility::Inherited };
+
+        match arrows.get(&boss).and_then(|&arrow| arrow_nodes.get_mut(arrow).ok()) {
+            Some((mut style, mut text, mut arrow_visibility)) => {
+                style.left = Val::Px(position.x);
+                style.top = Val::Px(position.y);
+                text.sections[0].value = glyph.to_string();
+                *arrow_visibility = visibility;
+            }
+            None => {
+                let arrow = commands
+                    .spawn(
+                        TextBundle::from_section(
+                            glyph,
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: ARROW_SIZE,
+                                color: BOSS_COLOR,
+                            },
+                        )
+                        .with_style(Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(position.x),
+                            top: Val::Px(position.y),
+                            ..default()
+                        }),
+                    )
+                    .insert(BossArrow)
+                    .insert(visibility)
+                    .id();
+                arrows.insert(boss, arrow);
+            }
+        }
+    }
+
+    arrows.retain(|&boss, &mut arrow| {
+        let alive = bosses.contains(boss);
+        if !alive {
+            commands.entity(arrow).despawn_recursive();
+        }
+        alive
+    });
+}
@@ -0,0 +1,322 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+use std::time::Duration;
+
+use rand::Rng;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{GameState, Level, GROUND_SIZE};
+
+const CELL_SIZE: f32 = 0.5;
+const AGENT_RADIUS: f32 = 0.8;
+const AGENT_HEIGHT: f32 = 1.0;
+const WAYPOINT_REACHED_DISTANCE: f32 = 0.3;
+const MAX_PATHS_PER_FRAME: usize = 4;
+
+// Walkability grid covering the ground, baked from level colliders
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    origin: Vec2,
+    width: usize,
+    depth: usize,
+    walkable: Vec<bool>,
+    baked: bool,
+}
+
+#[derive(Component)]
+pub struct PathFollower {
+    pub target: Vec3,
+    path: Vec<Vec3>,
+    repath_timer: Timer,
+    repath_pending: bool,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct OpenNode {
+    cost: f32,
+    index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, so BinaryHeap pops the cheapest node first
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct NavigationPlugin;
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .add_systems(
+                Update,
+                (
+                    bake_nav_grid,
+                    update_paths
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+impl PathFollower {
+    pub fn new(repath_interval: f32) -> Self {
+        // Start timer at random point, so agents spawned together don't repath in the same frame
+        let mut repath_timer = Timer::from_seconds(repath_interval, TimerMode::Repeating);
+        let offset = rand::thread_rng().gen_range(0.0..repath_interval);
+        repath_timer.set_elapsed(Duration::from_secs_f32(offset));
+
+        PathFollower {
+            target: Vec3::ZERO,
+            path: Vec::new(),
+            repath_timer,
+            repath_pending: true,
+        }
+    }
+
+    // Horizontal direction towards next waypoint, or straight towards target if there is no path
+    pub fn direction(&mut self, position: Vec3) -> Vec3 {
+        while let Some(waypoint) = self.path.first() {
+            let distance = Vec2::new(waypoint.x - position.x, waypoint.z - position.z).length();
+            if distance > WAYPOINT_REACHED_DISTANCE {
+                break;
+            }
+            self.path.remove(0);
+        }
+
+        let next = self.path.first().copied().unwrap_or(self.target);
+        Vec3::new(next.x - position.x, 0.0, next.z - position.z).normalize_or_zero()
+    }
+}
+
+impl NavGrid {
+    fn cell_at(&self, position: Vec3) -> Option<usize> {
+        let x = ((position.x - self.origin.x) / CELL_SIZE).floor();
+        let z = ((position.z - self.origin.y) / CELL_SIZE).floor();
+        if x < 0.0 || z < 0.0 || x >= self.width as f32 || z >= self.depth as f32 {
+            return None;
+        }
+        Some(z as usize * self.width + x as usize)
+    }
+
+    fn cell_center(&self, index: usize) -> Vec2 {
+        let x = (index % self.width) as f32;
+        let z = (index / self.width) as f32;
+        self.origin + Vec2::new(x + 0.5, z + 0.5) * CELL_SIZE
+    }
+
+    pub fn is_walkable(&self, position: Vec3) -> bool {
+        match self.cell_at(position) {
+            Some(index) => self.walkable[index],
+            None => false,
+        }
+    }
+
+    // Closest walkable cell, in case target stands right next to an obstacle
+    fn nearest_walkable(&self, index: usize) -> Option<usize> {
+        if self.walkable[index] {
+            return Some(index);
+        }
+        let (x, z) = ((index % self.width) as i32, (index / self.width) as i32);
+        for radius in 1..=3 {
+            for dz in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (nx, nz) = (x + dx, z + dz);
+                    if nx < 0 || nz < 0 || nx >= self.width as i32 || nz >= self.depth as i32 {
+                        continue;
+                    }
+                    let neighbour = nz as usize * self.width + nx as usize;
+                    if self.walkable[neighbour] {
+                        return Some(neighbour);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // Sample segment between two points to check it doesn't cross blocked cells
+    fn line_walkable(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = (from.distance(to) / (CELL_SIZE * 0.5)).ceil() as usize;
+        for step in 0..=steps {
+            let point = from.lerp(to, step as f32 / steps.max(1) as f32);
+            if !self.is_walkable(Vec3::new(point.x, 0.0, point.y)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn heuristic(&self, from: usize, to: usize) -> f32 {
+        let dx = ((from % self.width) as f32 - (to % self.width) as f32).abs();
+        let dz = ((from / self.width) as f32 - (to / self.width) as f32).abs();
+        dx.max(dz) + (SQRT_2 - 1.0) * dx.min(dz)
+    }
+
+    // A* over grid cells, returns waypoints from start (excluded) to goal
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Option<Vec<Vec3>> {
+        if !self.baked {
+            return None;
+        }
+
+        // No need to search when there is nothing in the way
+        if self.line_walkable(Vec2::new(start.x, start.z), Vec2::new(goal.x, goal.z)) {
+            return Some(vec![goal]);
+        }
+
+        let start_index = self.nearest_walkable(self.cell_at(start)?)?;
+        let goal_index = self.nearest_walkable(self.cell_at(goal)?)?;
+
+        let mut g_score = vec![f32::INFINITY; self.walkable.len()];
+        let mut came_from = vec![usize::MAX; self.walkable.len()];
+        let mut open = BinaryHeap::new();
+
+        g_score[start_index] = 0.0;
+        open.push(OpenNode {
+            cost: self.heuristic(start_index, goal_index),
+            index: start_index,
+        });
+
+        while let Some(OpenNode { cost, index }) = open.pop() {
+            if index == goal_index {
+                break;
+            }
+            // Skip stale heap entries
+            if cost - self.heuristic(index, goal_index) > g_score[index] + 0.001 {
+                continue;
+            }
+
+            let (x, z) = ((index % self.width) as i32, (index / self.width) as i32);
+            for dz in -1..=1 {
+                for dx in -1..=1 {
+                    if dx == 0 && dz == 0 {
+                        continue;
+                    }
+                    let (nx, nz) = (x + dx, z + dz);
+                    if nx < 0 || nz < 0 || nx >= self.width as i32 || nz >= self.depth as i32 {
+                        continue;
+                    }
+                    let neighbour = nz as usize * self.width + nx as usize;
+                    if !self.walkable[neighbour] {
+                        continue;
+                    }
+
+                    // Prevent cutting corners of obstacles when moving diagonally
+                    let diagonal = dx != 0 && dz != 0;
+                    if diagonal
+                        && (!self.walkable[z as usize * self.width + nx as usize]
+                            || !self.walkable[nz as usize * self.width + x as usize])
+                    {
+                        continue;
+                    }
+
+                    let step_cost = if diagonal { SQRT_2 } else { 1.0 };
+                    let tentative = g_score[index] + step_cost;
+                    if tentative < g_score[neighbour] {
+                        g_score[neighbour] = tentative;
+                        came_from[neighbour] = index;
+                        open.push(OpenNode {
+                            cost: tentative + self.heuristic(neighbour, goal_index),
+                            index: neighbour,
+                        });
+                    }
+                }
+            }
+        }
+
+        if start_index != goal_index && came_from[goal_index] == usize::MAX {
+            return None;
+        }
+
+        let mut cells = vec![goal_index];
+        let mut current = goal_index;
+        while current != start_index {
+            current = came_from[current];
+            cells.push(current);
+        }
+        cells.reverse();
+
+        // Drop waypoints that can be skipped by walking in straight line
+        let mut waypoints = Vec::new();
+        let mut anchor = Vec2::new(start.x, start.z);
+        let mut i = 1;
+        while i < cells.len() {
+            let mut furthest = i;
+            while furthest + 1 < cells.len()
+                && self.line_walkable(anchor, self.cell_center(cells[furthest + 1]))
+            {
+                furthest += 1;
+            }
+            anchor = self.cell_center(cells[furthest]);
+            waypoints.push(Vec3::new(anchor.x, start.y, anchor.y));
+            i = furthest + 1;
+        }
+
+        // Finish exactly on the goal instead of center of its cell
+        waypoints.pop();
+        waypoints.push(goal);
+        Some(waypoints)
+    }
+}
+
+fn bake_nav_grid(
+    mut nav_grid: ResMut<NavGrid>,
+    level: Query<(&Transform, &Collider), With<Level>>,
+    added_level: Query<(), Added<Level>>,
+) {
+    if added_level.is_empty() {
+        return;
+    }
+
+    let cells = (GROUND_SIZE / CELL_SIZE) as usize;
+    nav_grid.origin = Vec2::splat(-GROUND_SIZE / 2.0);
+    nav_grid.width = cells;
+    nav_grid.depth = cells;
+    nav_grid.walkable = vec![true; cells * cells];
+
+    // Cell is blocked when any level collider is closer to agent's center than its radius
+    for index in 0..nav_grid.walkable.len() {
+        let center = nav_grid.cell_center(index);
+        let point = Vec3::new(center.x, AGENT_HEIGHT, center.y);
+        nav_grid.walkable[index] = level.iter().all(|(transform, collider)| {
+            collider.distance_to_point(transform.translation, transform.rotation, point, true)
+                > AGENT_RADIUS
+        });
+    }
+    nav_grid.baked = true;
+}
+
+fn update_paths(
+    nav_grid: Res<NavGrid>,
+    mut followers: Query<(&Transform, &mut PathFollower)>,
+    time: Res<Time>,
+) {
+    let mut paths_this_frame = 0;
+    for (transform, mut follower) in followers.iter_mut() {
+        if follower.repath_timer.tick(time.delta()).just_finished() {
+            follower.repath_pending = true;
+        }
+
+        // Throttle number of searches, remaining agents get their turn in next frames
+        if !follower.repath_pending || paths_this_frame >= MAX_PATHS_PER_FRAME {
+            continue;
+        }
+        paths_this_frame += 1;
+        follower.repath_pending = false;
+
+        let target = follower.target;
+        follower.path = nav_grid
+            .find_path(transform.translation, target)
+            .unwrap_or_default();
+    }
+}
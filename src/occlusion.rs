@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::Player;
+use crate::camera::move_camera;
+use crate::{GameState, Level, MainCamera};
+
+// Level mesh currently see-through, its own material was swapped for a faded copy
+#[derive(Component)]
+struct Faded {
+    original: Handle<StandardMaterial>,
+    alpha: f32,
+}
+
+// Alpha of geometry hiding the player
+const FADED_ALPHA: f32 = 0.25;
+// Alpha change per second
+const FADE_SPEED: f32 = 4.0;
+// Points on the player that should stay visible, from feet up to head
+const PLAYER_POINTS: [f32; 2] = [0.0, 1.0];
+
+pub struct OcclusionPlugin;
+impl Plugin for OcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, fade_occluders.after(move_camera).in_set(GameState::Playing));
+    }
+}
+
+fn fade_occluders(
+    camera: Query<&Transform, With<MainCamera>>,
+    player: Query<&Transform, With<Player>>,
+    mut occluders: Query<(Entity, &mut Handle<StandardMaterial>, Option<&mut Faded>), With<Level>>,
+    rapier_context: Res<RapierContext>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let camera_position = camera.single().translation;
+
+    let mut blocking = HashSet::new();
+    for height in PLAYER_POINTS {
+        let target = player.translation + Vec3::Y * height;
+        let to_target = target - camera_position;
+        let distance = to_target.length();
+        if distance == 0.0 {
+            continue;
+        }
+        rapier_context.intersections_with_ray(
+            camera_position,
+            to_target / distance,
+            distance,
+            true,
+            QueryFilter::only_fixed(),
+            |entity, _| {
+                if occluders.contains(entity) {
+                    blocking.insert(entity);
+                }
+                true
+            },
+        );
+    }
+
+    for (entity, mut material, faded) in occluders.iter_mut() {
+        let is_blocking = blocking.contains(&entity);
+        let Some(mut faded) = faded else {
+            if !is_blocking {
+                continue;
+            }
+            // Copy the material so other meshes sharing it stay opaque
+            let Some(mut copy) = materials.get(&material).cloned() else {
+                continue;
+            };
+            copy.alpha_mode = AlphaMode::Blend;
+            let original = std::mem::replace(&mut *material, materials.add(copy));
+            commands.entity(entity).insert(Faded { original, alpha: 1.0 });
+            continue;
+        };
+
+        let target_alpha = if is_blocking { FADED_ALPHA } else { 1.0 };
+        let step = FADE_SPEED * time.delta_seconds();
+        faded.alpha += (target_alpha - faded.alpha).clamp(-step, step);
+
+        // Fully back, so original material can take over again
+        if !is_blocking && faded.alpha >= 1.0 {
+            *material = faded.original.clone();
+            commands.entity(entity).remove::<Faded>();
+            continue;
+        }
+        if let Some(faded_material) = materials.get_mut(&material) {
+            faded_material.base_color.set_a(faded.alpha);
+        }
+    }
+}
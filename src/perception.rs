@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::Player;
+use crate::GameState;
+
+// Player closer than this is noticed even when standing outside of view cone
+const HEARING_DISTANCE: f32 = 2.0;
+
+#[derive(Component)]
+pub struct Perception {
+    pub view_distance: f32,
+    pub view_angle: f32,
+    pub memory_duration: f32,
+    pub can_see_player: bool,
+    pub last_seen_position: Option<Vec3>,
+    time_since_seen: f32,
+}
+
+pub struct PerceptionPlugin;
+impl Plugin for PerceptionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_perception.in_set(GameState::Playing),
+        );
+    }
+}
+
+impl Perception {
+    // View angle is measured from forward direction to the edge of the cone
+    pub fn new(view_distance: f32, view_angle: f32, memory_duration: f32) -> Self {
+        Perception {
+            view_distance,
+            view_angle,
+            memory_duration,
+            can_see_player: false,
+            last_seen_position: None,
+            time_since_seen: 0.0,
+        }
+    }
+
+    pub fn is_aware(&self) -> bool {
+        self.last_seen_position.is_some()
+    }
+}
+
+fn update_perception(
+    mut perceivers: Query<(Entity, &Transform, &mut Perception), Without<Player>>,
+    allies: Query<(), With<Perception>>,
+    player: Query<(Entity, &Transform), With<Player>>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let Ok((player_entity, player_transform)) = player.get_single() else {
+        return;
+    };
+
+    // Other enemies don't block the view
+    let not_ally = |entity: Entity| !allies.contains(entity);
+
+    for (entity, transform, mut perception) in perceivers.iter_mut() {
+        let to_player = player_transform.translation - transform.translation;
+        let distance = to_player.length();
+
+        // Check player is inside the view cone
+        let forward = transform.rotation * Vec3::Z;
+        let flat_to_player = Vec3::new(to_player.x, 0.0, to_player.z);
+        let in_cone = distance < HEARING_DISTANCE
+            || forward.angle_between(flat_to_player) <= perception.view_angle;
+
+        // Then cast ray to make sure nothing stands between
+        let mut sees = false;
+        if distance < perception.view_distance && in_cone {
+            let filter = QueryFilter::default()
+                .exclude_collider(entity)
+                .predicate(&not_ally);
+            sees = match rapier_context.cast_ray(
+                transform.translation,
+                to_player.normalize_or_zero(),
+                distance,
+                true,
+                filter,
+            ) {
+                Some((hit_entity, _toi)) => hit_entity == player_entity,
+                None => true,
+            };
+        }
+
+        perception.can_see_player = sees;
+        if sees {
+            perception.last_seen_position = Some(player_transform.translation);
+            perception.time_since_seen = 0.0;
+        } else {
+            // Forget about player after a while
+            perception.time_since_seen += time.delta_seconds();
+            if perception.time_since_seen > perception.memory_duration {
+                perception.last_seen_position = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use bevy::prelude::*;
+use bevy::render::mesh::shape as render_shape;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::{Ammo, Inventory, Player};
+use crate::powerups::{PowerUpEvent, PowerUpKind};
+use crate::bosses::Boss;
+use crate::combat::{apply_damage, DeathEvent};
+use crate::weapons::{Arsenal, WeaponsAsset};
+use crate::{FloatingTextEvent, Game, GameState, Health, MaxHealth};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PickupKind {
+    Health,
+    Ammo,
+    // Unlocks random weapon player doesn't have yet
+    Weapon,
+    PowerUp(PowerUpKind),
+    ScoreGem,
+}
+
+#[derive(Component)]
+pub struct Pickup {
+    kind: PickupKind,
+    lifetime: Timer,
+}
+
+pub struct LootTable {
+    // Chance that anything drops at all
+    pub drop_chance: f32,
+    pub entries: Vec<(PickupKind, u32)>,
+}
+
+// World table spawns pickups over time, the others are rolled on kills
+#[derive(Resource)]
+pub struct LootTables {
+    pub world: LootTable,
+    pub enemy: LootTable,
+    pub boss: LootTable,
+}
+
+impl Default for LootTables {
+    fn default() -> Self {
+        LootTables {
+            world: LootTable {
+                drop_chance: 1.0,
+                entries: vec![
+                    (PickupKind::Health, 4),
+                    (PickupKind::Ammo, 4),
+                    (PickupKind::Weapon, 2),
+                    (PickupKind::PowerUp(PowerUpKind::DoubleDamage), 1),
+                    (PickupKind::PowerUp(PowerUpKind::RapidFire), 1),
+                    (PickupKind::PowerUp(PowerUpKind::SpeedBoost), 1),
+                    (PickupKind::PowerUp(PowerUpKind::Shield), 1),
+                    (PickupKind::PowerUp(PowerUpKind::MultiShot), 1),
+                ],
+            },
+            enemy: LootTable {
+                drop_chance: 0.25,
+                entries: vec![
+                    (PickupKind::Ammo, 5),
+                    (PickupKind::Health, 2),
+                    (PickupKind::ScoreGem, 3),
+                ],
+            },
+            boss: LootTable {
+                drop_chance: 1.0,
+                entries: vec![
+                    (PickupKind::Weapon, 3),
+                    (PickupKind::PowerUp(PowerUpKind::DoubleDamage), 1),
+                    (PickupKind::PowerUp(PowerUpKind::Shield), 1),
+                    (PickupKind::ScoreGem, 2),
+                ],
+            },
+        }
+    }
+}
+
+#[derive(Resource)]
+struct PickupSpawnTimer(Timer);
+
+#[derive(Resource)]
+struct PickupAssets {
+    pumpkin: Handle<Scene>,
+}
+
+const MAX_WORLD_PICKUPS: usize = 4;
+const PICKUP_LIFETIME: f32 = 12.0;
+// Pickup blinks for this long before it disappears
+const BLINK_TIME: f32 = 3.0;
+const SCORE_GEM_VALUE: u32 = 25;
+const HEALTH_PICKUP_VALUE: i32 = 10;
+
+pub struct PickupsPlugin;
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LootTables>()
+            .insert_resource(PickupSpawnTimer(Timer::from_seconds(
+                5.0,
+                TimerMode::Repeating,
+            )))
+            .add_systems(Startup, load_pickup_assets)
+            .add_systems(
+                Update,
+                (
+                    spawn_world_pickups,
+                    drop_loot.after(apply_damage),
+                    collect_pickups,
+                    expire_pickups,
+                )
+                .in_set(GameState::Playing),
+            );
+    }
+}
+
+impl Pickup {
+    pub fn kind(&self) -> PickupKind {
+        self.kind
+    }
+}
+
+impl LootTable {
+    pub fn roll(&self) -> Option<PickupKind> {
+        let mut rng = rand::thread_rng();
+        if rng.gen::<f32>() >= self.drop_chance {
+            return None;
+        }
+        self.entries
+            .choose_weighted(&mut rng, |entry| entry.1)
+            .ok()
+            .map(|entry| entry.0)
+    }
+}
+
+impl PickupKind {
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::Health => Color::ORANGE,
+            PickupKind::Ammo => Color::GOLD,
+            PickupKind::Weapon => Color::CYAN,
+            PickupKind::PowerUp(power_up) => power_up.color(),
+            PickupKind::ScoreGem => Color::PURPLE,
+        }
+    }
+}
+
+fn load_pickup_assets(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(PickupAssets {
+        pumpkin: asset_server.load("models/pumpkin.glb#Scene0"),
+    });
+}
+
+fn spawn_pickup(
+    kind: PickupKind,
+    position: Vec3,
+    pickup_assets: &PickupAssets,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    commands: &mut Commands,
+) {
+    let mesh = match kind {
+        // Pumpkin heals, the rest are simple shapes in color of their kind
+        PickupKind::Health => None,
+        PickupKind::Ammo => Some(Mesh::from(render_shape::Box::new(0.5, 0.3, 0.3))),
+        PickupKind::Weapon => Some(Mesh::from(render_shape::Box::new(0.8, 0.3, 0.3))),
+        PickupKind::PowerUp(_) => Some(Mesh::from(render_shape::UVSphere {
+            radius: 0.25,
+            ..default()
+        })),
+        PickupKind::ScoreGem => Some(Mesh::from(render_shape::Cube::new(0.3))),
+    };
+    let mut pickup = match mesh {
+        Some(mesh) => commands.spawn(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(kind.color().into()),
+            transform: Transform::from_xyz(position.x, 0.3, position.z),
+            ..default()
+        }),
+        None => commands.spawn(SceneBundle {
+            transform: Transform {
+                translation: Vec3::new(position.x, 0.5, position.z),
+                scale: Vec3::new(2.0, 2.0, 2.0),
+                ..default()
+            },
+            scene: pickup_assets.pumpkin.clone(),
+            ..default()
+        }),
+    };
+    pickup
+        .with_children(|children| {
+            children.spawn(PointLightBundle {
+                point_light: PointLight {
+                    color: kind.color(),
+                    intensity: 500.0,
+                    range: 5.0,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 2.0, 0.0),
+                ..default()
+            });
+        })
+        .insert(Pickup {
+            kind,
+            lifetime: Timer::from_seconds(PICKUP_LIFETIME, TimerMode::Once),
+        });
+}
+
+fn spawn_world_pickups(
+    time: Res<Time>,
+    mut timer: ResMut<PickupSpawnTimer>,
+    pickups: Query<&Pickup>,
+    player: Query<&Transform, With<Player>>,
+    loot_tables: Res<LootTables>,
+    pickup_assets: Res<PickupAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    // Make sure we wait enough time before spawning the next pickup
+    if !timer.0.tick(time.delta()).finished() || pickups.iter().count() >= MAX_WORLD_PICKUPS {
+        return;
+    }
+    let Some(kind) = loot_tables.world.roll() else {
+        return;
+    };
+
+    // Ensure pickup doesn't spawn on the player
+    let player_position = player.single().translation;
+    let mut rng = rand::thread_rng();
+    let position = loop {
+        let position = Vec3::new(rng.gen_range(-7.0..7.0), 0.0, rng.gen_range(-7.0..7.0));
+        if Vec2::new(player_position.x, player_position.z).distance(Vec2::new(position.x, position.z)) > 2.0 {
+            break position;
+        }
+    };
+    spawn_pickup(kind, position, &pickup_assets, &mut meshes, &mut materials, &mut commands);
+}
+
+// Killed enemies and bosses drop loot from their table
+fn drop_loot(
+    bosses: Query<(), With<Boss>>,
+    loot_tables: Res<LootTables>,
+    pickup_assets: Res<PickupAssets>,
+    mut death_event_reader: EventReader<DeathEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for event in death_event_reader.iter() {
+        let loot_table = if bosses.contains(event.entity) {
+            &loot_tables.boss
+        } else {
+            &loot_tables.enemy
+        };
+        if let Some(kind) = loot_table.roll() {
+            spawn_pickup(kind, event.translation, &pickup_assets, &mut meshes, &mut materials, &mut commands);
+        }
+    }
+}
+
+fn collect_pickups(
+    mut player: Query<(Entity, &mut Health, &MaxHealth, &mut Ammo, &mut Inventory), With<Player>>,
+    pickups: Query<(Entity, &Transform, &Pickup)>,
+    mut game: ResMut<Game>,
+    arsenal: Res<Arsenal>,
+    weapon_assets: Res<Assets<WeaponsAsset>>,
+    rapier_context: Res<RapierContext>,
+    mut power_up_event_writer: EventWriter<PowerUpEvent>,
+    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
+    mut commands: Commands,
+) {
+    let (player_entity, mut health, max_health, mut ammo, mut inventory) = player.single_mut();
+    for (pickup_entity, pickup_transform, pickup) in pickups.iter() {
+        let shape = Collider::ball(0.5);
+        let filter = QueryFilter::default();
+        let mut touched = false;
+        rapier_context.intersections_with_shape(pickup_transform.translation, pickup_transform.rotation, &shape, filter, |entity| {
+            touched |= entity == player_entity;
+            true
+        });
+        if !touched {
+            continue;
+        }
+        commands.entity(pickup_entity).despawn_recursive();
+
+        let text = match pickup.kind {
+            PickupKind::Health => {
+                health.0 = (health.0 + HEALTH_PICKUP_VALUE).min(max_health.0);
+                format!("+{} HP", HEALTH_PICKUP_VALUE)
+            }
+            PickupKind::Ammo => {
+                ammo.refill();
+                "Ammo".to_string()
+            }
+            PickupKind::Weapon => {
+                // Weapon player already has everything of counts as ammo
+                let definitions = arsenal.definitions(&weapon_assets).unwrap_or(&[]);
+                let locked: Vec<usize> = (0..definitions.len()).filter(|&index| !inventory.has(index)).collect();
+                match locked.choose(&mut rand::thread_rng()) {
+                    Some(&index) => {
+                        inventory.unlock(index);
+                        definitions[index].name.clone()
+                    }
+                    None => {
+                        ammo.refill();
+                        "Ammo".to_string()
+                    }
+                }
+            }
+            PickupKind::PowerUp(kind) => {
+                power_up_event_writer.send(PowerUpEvent {
+                    target: player_entity,
+                    kind,
+                });
+                kind.name().to_string()
+            }
+            PickupKind::ScoreGem => {
+                game.score += SCORE_GEM_VALUE;
+                format!("+{}", SCORE_GEM_VALUE)
+            }
+        };
+        floating_text_event_writer.send(FloatingTextEvent {
+            translation: pickup_transform.translation,
+            text,
+            color: pickup.kind.color(),
+            size: 40.0,
+        });
+    }
+}
+
+// Pickups blink before they disappear
+fn expire_pickups(
+    mut pickups: Query<(Entity, &mut Pickup, &mut Visibility)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut pickup, mut visibility) in pickups.iter_mut() {
+        if pickup.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let remaining = pickup.lifetime.remaining_secs();
+        *visibility = if remaining < BLINK_TIME && (remaining * 8.0) as u32 % 2 == 0 {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
@@ -1,12 +1,20 @@
 use std::f32::consts::FRAC_PI_2;
 
+use rand::Rng;
 use bevy::prelude::*;
 use bevy::render::mesh::shape as render_shape;
 use bevy_rapier3d::prelude::*;
 
-use crate::{GameState, Health, Game, Cursor, FloatingTextEvent};
-use crate::enemies::Enemy;
-use crate::bosses::Boss;
+use crate::{GameState, Health, MaxHealth, Game, Cursor, MainCamera};
+use crate::combat::DamageEvent;
+use crate::actions::Action;
+use crate::gamepad::GamepadControls;
+use crate::camera::camera_basis;
+use crate::melee::MeleeCombo;
+use crate::animation::AnimationController;
+use crate::powerups::{MultiShot, PowerUp, RapidFire, SpeedBoost};
+use crate::status::{StatusEffects, StatusEvent};
+use crate::weapons::{fire_flame, fire_grenade, fire_rail, hit_target, Arsenal, Hit, FireMode, Projectile, WeaponDefinition, WeaponsAsset};
 
 #[derive(Component)]
 pub struct Player;
@@ -14,39 +22,62 @@ pub struct Player;
 #[derive(Component)]
 struct Bullet {
     direction: Vec3,
-    start_position: Vec3
+    start_position: Vec3,
+    speed: f32,
+    range: f32,
+    hit: Hit,
 }
 
-#[derive(PartialEq)]
-enum WeaponType {
-    Pistol,
-    Shotgun,
-    Rifle
+// Index of current weapon in arsenal definitions
+#[derive(Component)]
+pub struct Weapon(usize);
+
+struct WeaponAmmo {
+    magazine: u32,
+    reserve: u32,
+    magazine_size: u32,
+    max_reserve: Option<u32>,
+    reload_time: f32,
 }
 
 #[derive(Component)]
-pub struct Weapon(WeaponType);
+pub struct Ammo {
+    weapons: Vec<WeaponAmmo>,
+    reload_timer: Option<Timer>,
+}
 
-#[derive(Resource)]
-struct RifleCooldownTimer(Timer);
+// Weapons player has picked up, as indices into arsenal definitions
+#[derive(Component)]
+pub struct Inventory {
+    unlocked: Vec<usize>,
+    // Wheel movement not yet turned into a weapon switch
+    scroll: f32,
+}
+
+// Trigger state of current weapon
+#[derive(Component, Default)]
+struct Trigger {
+    cooldown: f32,
+    burst_remaining: u32,
+    burst_timer: f32,
+    charge: f32,
+    recoil: f32,
+}
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
-            .insert_resource(RifleCooldownTimer(Timer::from_seconds(
-                0.1,
-                TimerMode::Repeating,
-            )))    
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
                 (
                     move_player,
-                    player_melee_attack,
                     player_shoot_attack,
                     move_player_bullets,
-                    change_weapon,
+                    select_weapon,
+                    sync_ammo.before(player_shoot_attack),
+                    reload_weapon,
                 )
                 .in_set(GameState::Playing),
             );
@@ -61,8 +92,15 @@ fn setup(
     game.player = Some(
         commands
             .spawn(Player)
-            .insert(Health(5))
-            .insert(Weapon(WeaponType::Pistol))
+            .insert(Health(50))
+            .insert(MaxHealth(50))
+            .insert(Weapon(0))
+            .insert(Ammo::new())
+            .insert(Inventory::new())
+            .insert(Trigger::default())
+            .insert(MeleeCombo::default())
+            .insert(AnimationController::new())
+            .insert(StatusEffects::new(&[]))
             .insert(PbrBundle {
                 transform: Transform::from_xyz(0.0, 1.0, 0.0),
                 ..default()
@@ -85,50 +123,192 @@ fn setup(
             .insert(Collider::capsule_y(0.5, 0.5))
             .insert(Restitution::coefficient(0.7))
             .id(),
-    )
+    );
+}
+
+impl WeaponAmmo {
+    fn new(definition: &WeaponDefinition) -> Self {
+        WeaponAmmo {
+            magazine: definition.magazine_size,
+            reserve: definition.magazine_size * 2,
+            magazine_size: definition.magazine_size,
+            max_reserve: definition.max_reserve,
+            reload_time: definition.reload_time,
+        }
+    }
+}
+
+impl Weapon {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl Ammo {
+    fn new() -> Self {
+        Ammo {
+            weapons: Vec::new(),
+            reload_timer: None,
+        }
+    }
+
+    // Take one round from magazine if weapon is ready to fire
+    fn try_fire(&mut self, weapon: usize) -> bool {
+        if self.reload_timer.is_some() {
+            return false;
+        }
+        // Ammo isn't built until weapon definitions load
+        let Some(ammo) = self.weapons.get_mut(weapon) else {
+            return false;
+        };
+        if ammo.magazine == 0 {
+            return false;
+        }
+        ammo.magazine -= 1;
+        true
+    }
+
+    fn start_reload(&mut self, weapon: usize) {
+        let Some(ammo) = self.weapons.get(weapon) else {
+            return;
+        };
+        let has_reserve = ammo.reserve > 0 || ammo.max_reserve.is_none();
+        if self.reload_timer.is_none() && ammo.magazine < ammo.magazine_size && has_reserve {
+            self.reload_timer = Some(Timer::from_seconds(ammo.reload_time, TimerMode::Once));
+        }
+    }
+
+    fn finish_reload(&mut self, weapon: usize) {
+        self.reload_timer = None;
+        let Some(ammo) = self.weapons.get_mut(weapon) else {
+            return;
+        };
+        let missing = ammo.magazine_size - ammo.magazine;
+        match ammo.max_reserve {
+            Some(_) => {
+                let loaded = missing.min(ammo.reserve);
+                ammo.magazine += loaded;
+                ammo.reserve -= loaded;
+            }
+            None => ammo.magazine += missing,
+        }
+    }
+
+    // Rounds in magazine and spare ones, None when spare ammo is unlimited
+    pub fn rounds(&self, weapon: usize) -> Option<(u32, Option<u32>)> {
+        self.weapons
+            .get(weapon)
+            .map(|ammo| (ammo.magazine, ammo.max_reserve.map(|_| ammo.reserve)))
+    }
+
+    pub fn reloading(&self) -> bool {
+        self.reload_timer.is_some()
+    }
+
+    // Ammo pickup gives every weapon one magazine of spare ammo
+    pub fn refill(&mut self) {
+        for ammo in self.weapons.iter_mut() {
+            if let Some(max_reserve) = ammo.max_reserve {
+                ammo.reserve = (ammo.reserve + ammo.magazine_size).min(max_reserve);
+            }
+        }
+    }
+}
+
+impl Inventory {
+    // Player always starts with the first weapon
+    fn new() -> Self {
+        Inventory {
+            unlocked: vec![0],
+            scroll: 0.0,
+        }
+    }
+
+    pub fn unlocked(&self) -> &[usize] {
+        &self.unlocked
+    }
+
+    pub fn has(&self, weapon: usize) -> bool {
+        self.unlocked.contains(&weapon)
+    }
+
+    // Returns false if weapon was already unlocked
+    pub fn unlock(&mut self, weapon: usize) -> bool {
+        if self.has(weapon) {
+            return false;
+        }
+        self.unlocked.push(weapon);
+        self.unlocked.sort();
+        true
+    }
+
+    // Unlocked weapon that is `steps` away from current one, wrapping around
+    fn cycle(&self, current: usize, steps: i32) -> usize {
+        let position = self.unlocked.iter().position(|&weapon| weapon == current).unwrap_or(0);
+        let count = self.unlocked.len() as i32;
+        let next = (position as i32 + steps).rem_euclid(count);
+        self.unlocked[next as usize]
+    }
 }
 
 fn move_player(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut player: Query<(&mut Velocity, &mut Transform), With<Player>>,
+    actions: Res<Input<Action>>,
+    gamepad: Res<GamepadControls>,
+    mut player: Query<(&mut Velocity, &mut Transform, &StatusEffects, Option<&PowerUp<SpeedBoost>>), With<Player>>,
     cursor_transform: Query<&Transform, (With<Cursor>, Without<Player>)>,
+    camera_transform: Query<&Transform, (With<MainCamera>, Without<Player>)>,
     game: ResMut<Game>,
     rapier_context: Res<RapierContext>,
     time: Res<Time>,
 ) {
     const SPEED: f32 = 250.0;
-    let (mut vel, mut transform) = player.single_mut();
+    // Every stack of speed power-up adds this much of base speed
+    const BOOST_PER_STACK: f32 = 0.3;
+    let (mut vel, mut transform, status_effects, speed_boost) = player.single_mut();
+    let speed = SPEED
+        * (1.0 + speed_boost.map_or(0.0, |boost| boost.stacks as f32 * BOOST_PER_STACK))
+        * status_effects.speed_multiplier();
 
-    // Rotate character using cursor
-    let x_pos = cursor_transform.single().translation.x - transform.translation.x;
-    let z_pos = cursor_transform.single().translation.z - transform.translation.z;
-    let angle = (x_pos).atan2(z_pos) - FRAC_PI_2;
-    transform.rotation = Quat::from_rotation_y(angle);
+    // Rotate character using cursor, frozen player stays turned where it was
+    if status_effects.can_move() {
+        let x_pos = cursor_transform.single().translation.x - transform.translation.x;
+        let z_pos = cursor_transform.single().translation.z - transform.translation.z;
+        let angle = (x_pos).atan2(z_pos) - FRAC_PI_2;
+        transform.rotation = Quat::from_rotation_y(angle);
+    }
 
     let mut x = 0.0;
     let mut z = 0.0;
-    if keyboard_input.any_pressed([KeyCode::Up, KeyCode::W]) {
+    if actions.pressed(Action::MoveUp) {
         x = 1.0;
-    } else if keyboard_input.any_pressed([KeyCode::Down, KeyCode::S]) {
+    } else if actions.pressed(Action::MoveDown) {
         x = -1.0;
     }
 
-    if keyboard_input.any_pressed([KeyCode::Left, KeyCode::A]) {
+    if actions.pressed(Action::MoveLeft) {
         z = -1.0;
-    } else if keyboard_input.any_pressed([KeyCode::Right, KeyCode::D]) {
+    } else if actions.pressed(Action::MoveRight) {
         z = 1.0;
     }
 
+    // Left stick wins over keys, pushing it halfway walks at half speed
+    if gamepad.movement != Vec2::ZERO {
+        x = gamepad.movement.y;
+        z = gamepad.movement.x;
+    }
+
     if x == 0.0 && z == 0.0 {
         vel.linvel[0] = 0.0;
         vel.linvel[2] = 0.0;
     } else {
-        let v2_norm = Vec2::new(x, z).normalize();
-        vel.linvel[0] = v2_norm.x * SPEED * time.delta_seconds();
-        vel.linvel[2] = v2_norm.y * SPEED * time.delta_seconds();
+        // Up on the keyboard is up on the screen, whichever way camera is turned
+        let (forward, right) = camera_basis(camera_transform.single());
+        let direction = (forward * x + right * z).clamp_length_max(1.0);
+        vel.linvel[0] = direction.x * speed * time.delta_seconds();
+        vel.linvel[2] = direction.z * speed * time.delta_seconds();
     }
 
-    if keyboard_input.just_pressed(KeyCode::Space) {
+    if actions.just_pressed(Action::Jump) && status_effects.can_act() {
         // Prevent double-jump using raycast
         let ray_pos = transform.translation;
         let ray_dir = Vec3::new(0.0, -1.0, 0.0);
@@ -150,94 +330,201 @@ fn move_player(
     // vel.linvel[1] -= 1.0;
 }
 
-fn player_melee_attack(
-    mut enemies: Query<(Entity, &mut Health, &Transform), (With<Enemy>, Without<Player>)>,
-    mut player_transform: Query<&Transform, (With<Player>, Without<Enemy>)>,
-    rapier_context: Res<RapierContext>,
-    mut commands: Commands,
-    mouse: Res<Input<MouseButton>>,
-    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
-) {
-    if mouse.just_pressed(MouseButton::Right) {
-        for (enemy_entity, mut enemy_health, enemy_transform) in enemies.iter_mut() {
-            let shape = Collider::ball(1.0);
-            let shape_pos = player_transform.single_mut().translation;
-            let shape_rot = player_transform.single_mut().rotation;
-            let filter = QueryFilter::default();
-
-            rapier_context.intersections_with_shape(
-                shape_pos,
-                shape_rot,
-                &shape,
-                filter,
-                |entity| {
-                    if entity == enemy_entity {
-                        enemy_health.0 -= 1;
-                        
-                        // Create floating text
-                        floating_text_event_writer.send(FloatingTextEvent {
-                            translation: enemy_transform.translation,
-                            text: "-1".into(),
-                            color: Color::rgb(0.7, 0.0, 0.0),
-                        });
-
-                        if enemy_health.0 == 0 {
-                            commands.entity(enemy_entity).despawn_recursive();
-                        }
-                    }
-                    true
-                },
-            );
-        }
-    }
-}
-
 fn player_shoot_attack(
-    mut player: Query<(&Transform, &Weapon), (With<Player>, Without<Cursor>)>,
+    mut player: Query<
+        (
+            Entity,
+            &Transform,
+            &Weapon,
+            &mut Ammo,
+            &mut Trigger,
+            &StatusEffects,
+            Option<&PowerUp<RapidFire>>,
+            Option<&PowerUp<MultiShot>>,
+        ),
+        (With<Player>, Without<Cursor>),
+    >,
     mut cursor_transform: Query<&Transform, (With<Cursor>, Without<Player>)>,
-    mouse: Res<Input<MouseButton>>,
+    targets: Query<(Entity, &Transform), (With<Health>, Without<Player>)>,
+    actions: Res<Input<Action>>,
+    arsenal: Res<Arsenal>,
+    weapon_assets: Res<Assets<WeaponsAsset>>,
+    rapier_context: Res<RapierContext>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut status_event_writer: EventWriter<StatusEvent>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut commands: Commands,
     time: Res<Time>,
-    mut timer: ResMut<RifleCooldownTimer>,
 ) {
-    let mut direction: Vec3 = cursor_transform.single_mut().translation - player.single_mut().0.translation;
-    let direction_vec2 = Vec2::new(direction.x, direction.z).normalize();
+    const RECOIL_RECOVERY: f32 = 0.3;
+    let Some(definitions) = arsenal.definitions(&weapon_assets) else {
+        return;
+    };
+    let (player_entity, player_transform, weapon, mut ammo, mut trigger, status_effects, rapid_fire, multi_shot) = player.single_mut();
+    let Some(definition) = definitions.get(weapon.0) else {
+        return;
+    };
+    // Frozen or stunned player can't pull the trigger
+    if !status_effects.can_act() {
+        trigger.charge = 0.0;
+        trigger.burst_remaining = 0;
+        return;
+    }
+
+    let mut direction: Vec3 = cursor_transform.single_mut().translation - player_transform.translation;
+    let aim_distance = Vec2::new(direction.x, direction.z).length();
+    // Zero when aiming straight down at the player, there is no direction to shoot in then
+    let direction_vec2 = Vec2::new(direction.x, direction.z).normalize_or_zero();
     direction = Vec3::new(direction_vec2.x, 0.0, direction_vec2.y);
 
-    if mouse.just_pressed(MouseButton::Left) {
-        match player.single_mut().1.0 {
-            WeaponType::Pistol => {
-                spawn_bullet(player.single_mut().0.translation, direction, &mut meshes, &mut materials, &mut commands);
-            },
-            WeaponType::Shotgun => {
-                spawn_bullet(player.single_mut().0.translation, Quat::from_rotation_y(-0.6) * direction, &mut meshes, &mut materials, &mut commands);
-                spawn_bullet(player.single_mut().0.translation, Quat::from_rotation_y(-0.3) * direction, &mut meshes, &mut materials, &mut commands);
-                spawn_bullet(player.single_mut().0.translation, direction, &mut meshes, &mut materials, &mut commands);
-                spawn_bullet(player.single_mut().0.translation, Quat::from_rotation_y(0.3) * direction, &mut meshes, &mut materials, &mut commands);
-                spawn_bullet(player.single_mut().0.translation, Quat::from_rotation_y(0.6) * direction, &mut meshes, &mut materials, &mut commands);
-            },
-            _ => {}
-        }     
-    }
-
-    if mouse.pressed(MouseButton::Left) {
-        if !timer.0.tick(time.delta()).finished() {
-            return;
+    trigger.cooldown -= time.delta_seconds() * status_effects.attack_speed_multiplier();
+    trigger.recoil = (trigger.recoil - RECOIL_RECOVERY * time.delta_seconds()).max(0.0);
+
+    // Decide if weapon fires this frame based on its fire mode
+    let mut fire = false;
+    match definition.fire_mode {
+        FireMode::Semi => {
+            fire = actions.just_pressed(Action::Fire) && trigger.cooldown <= 0.0;
+        }
+        FireMode::Auto => {
+            fire = actions.pressed(Action::Fire) && trigger.cooldown <= 0.0;
+        }
+        FireMode::Burst { shots, interval } => {
+            if actions.just_pressed(Action::Fire) && trigger.cooldown <= 0.0 && trigger.burst_remaining == 0 {
+                trigger.burst_remaining = shots;
+                trigger.burst_timer = 0.0;
+            }
+            if trigger.burst_remaining > 0 {
+                trigger.burst_timer -= time.delta_seconds();
+                if trigger.burst_timer <= 0.0 {
+                    fire = true;
+                    trigger.burst_remaining -= 1;
+                    trigger.burst_timer = interval;
+                }
+            }
+        }
+        FireMode::Charge { charge_time } => {
+            if actions.pressed(Action::Fire) && trigger.cooldown <= 0.0 {
+                trigger.charge += time.delta_seconds();
+            }
+            if actions.just_released(Action::Fire) {
+                fire = trigger.charge >= charge_time;
+                trigger.charge = 0.0;
+            }
+        }
+    }
+
+    if !fire || direction == Vec3::ZERO {
+        return;
+    }
+    if !ammo.try_fire(weapon.0) {
+        trigger.burst_remaining = 0;
+        return;
+    }
+    let fire_rate = if rapid_fire.is_some() { definition.fire_rate * 2.0 } else { definition.fire_rate };
+    trigger.cooldown = 1.0 / fire_rate;
+
+    // Multi-shot adds two pellets per stack and makes sure they spread out
+    let extra_pellets = multi_shot.map_or(0, |multi_shot| multi_shot.stacks * 2);
+    let pellets = definition.pellets + extra_pellets;
+    let spread = definition.spread.max(0.25 * extra_pellets as f32);
+
+    // Fan pellets evenly across spread, recoil kicks the whole shot randomly
+    let kick = if trigger.recoil > 0.0 {
+        rand::thread_rng().gen_range(-trigger.recoil..trigger.recoil)
+    } else {
+        0.0
+    };
+    let origin = player_transform.translation;
+    for pellet in 0..pellets {
+        let angle = if pellets > 1 {
+            -spread / 2.0 + spread * pellet as f32 / (pellets - 1) as f32
+        } else {
+            0.0
+        };
+        let pellet_direction = Quat::from_rotation_y(angle + kick) * direction;
+        match definition.projectile {
+            Projectile::Bullet => {
+                spawn_bullet(origin, pellet_direction, definition, &mut meshes, &mut materials, &mut commands);
+            }
+            Projectile::Grenade { blast_radius } => {
+                fire_grenade(origin, pellet_direction, aim_distance, blast_radius, definition, player_entity, &mut meshes, &mut materials, &mut commands);
+            }
+            Projectile::Flame { cone_angle } => {
+                fire_flame(origin, pellet_direction, cone_angle, definition, player_entity, &targets, &rapier_context, &mut damage_event_writer, &mut status_event_writer, &mut meshes, &mut materials, &mut commands);
+            }
+            Projectile::Rail => {
+                fire_rail(origin, pellet_direction, definition, player_entity, &targets, &rapier_context, &mut damage_event_writer, &mut status_event_writer, &mut meshes, &mut materials, &mut commands);
+            }
+        }
+    }
+    trigger.recoil += definition.recoil;
+}
+
+// Build ammo for every weapon once definitions load, and again when they get edited
+fn sync_ammo(
+    mut player: Query<&mut Ammo, With<Player>>,
+    arsenal: Res<Arsenal>,
+    weapon_assets: Res<Assets<WeaponsAsset>>,
+    mut asset_events: EventReader<AssetEvent<WeaponsAsset>>,
+) {
+    let mut changed = false;
+    for event in asset_events.iter() {
+        if let AssetEvent::Created { .. } | AssetEvent::Modified { .. } = event {
+            changed = true;
         }
-        match player.single_mut().1.0 {
-            WeaponType::Rifle => {
-                spawn_bullet(player.single_mut().0.translation, direction, &mut meshes, &mut materials, &mut commands);
-            },
-            _ => {}
+    }
+    let Some(definitions) = arsenal.definitions(&weapon_assets) else {
+        return;
+    };
+    if !changed {
+        return;
+    }
+
+    let mut ammo = player.single_mut();
+    for (index, definition) in definitions.iter().enumerate() {
+        match ammo.weapons.get_mut(index) {
+            Some(weapon_ammo) => {
+                weapon_ammo.magazine_size = definition.magazine_size;
+                weapon_ammo.max_reserve = definition.max_reserve;
+                weapon_ammo.reload_time = definition.reload_time;
+                weapon_ammo.magazine = weapon_ammo.magazine.min(definition.magazine_size);
+            }
+            None => ammo.weapons.push(WeaponAmmo::new(definition)),
         }
     }
+    ammo.weapons.truncate(definitions.len());
+}
+
+fn reload_weapon(
+    mut player: Query<(&Weapon, &mut Ammo), With<Player>>,
+    actions: Res<Input<Action>>,
+    time: Res<Time>,
+) {
+    let (weapon, mut ammo) = player.single_mut();
+    let Some(weapon_ammo) = ammo.weapons.get(weapon.0) else {
+        return;
+    };
+
+    // Reload when asked or automatically when magazine runs dry
+    if actions.just_pressed(Action::Reload) || weapon_ammo.magazine == 0 {
+        ammo.start_reload(weapon.0);
+    }
+
+    let mut reloaded = false;
+    if let Some(reload_timer) = ammo.reload_timer.as_mut() {
+        reloaded = reload_timer.tick(time.delta()).finished();
+    }
+    if reloaded {
+        ammo.finish_reload(weapon.0);
+    }
 }
 
 fn spawn_bullet(
     origin: Vec3,
     direction: Vec3,
+    definition: &WeaponDefinition,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     commands: &mut Commands,
@@ -256,7 +543,10 @@ fn spawn_bullet(
         })
         .insert(Bullet {
             direction,
-            start_position: origin
+            start_position: origin,
+            speed: definition.projectile_speed,
+            range: definition.range,
+            hit: definition.hit(),
         })
         .insert(RigidBody::Dynamic)
         .insert(Velocity::zero());
@@ -267,22 +557,22 @@ fn move_player_bullets(
         (Entity, &mut Velocity, &Bullet, &Transform),
         With<Bullet>,
     >,
-    mut enemies: Query<(Entity, &mut Health), (With<Enemy>, Without<Boss>)>,
-    mut bosses: Query<(Entity, &mut Health), (With<Boss>, Without<Enemy>)>,
+    damageable: Query<(), (With<Health>, Without<Player>)>,
     rapier_context: Res<RapierContext>,
     mut commands: Commands,
     game: ResMut<Game>,
     time: Res<Time>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut status_event_writer: EventWriter<StatusEvent>,
 ) {
-    const SPEED: f32 = 600.0;
     for (bullet_entity, mut vel, bullet_struct, transform) in bullets.iter_mut() {
         // Despawn bullet after certain distance traveled
-        if bullet_struct.start_position.distance(transform.translation) > 20.0 {
+        if bullet_struct.start_position.distance(transform.translation) > bullet_struct.range {
             commands.entity(bullet_entity).despawn_recursive();
         }
 
-        vel.linvel[0] = bullet_struct.direction.x * SPEED * time.delta_seconds();
-        vel.linvel[2] = bullet_struct.direction.z * SPEED * time.delta_seconds();
+        vel.linvel[0] = bullet_struct.direction.x * bullet_struct.speed * time.delta_seconds();
+        vel.linvel[2] = bullet_struct.direction.z * bullet_struct.speed * time.delta_seconds();
 
         let shape = Collider::ball(0.1);
         let shape_pos = transform.translation;
@@ -299,41 +589,65 @@ fn move_player_bullets(
         {
             // Despawn bullet after it hits anything
             commands.entity(bullet_entity).despawn_recursive();
-            
-            // Manage enemy health
-            for (enemy_entity, mut enemy_health) in enemies.iter_mut() {
-                if entity == enemy_entity {
-                    enemy_health.0 -= 1;
-                    if enemy_health.0 == 0 {
-                        commands.entity(enemy_entity).despawn_recursive();
-                    }
-                }
-            }
 
-            // Manage boss health
-            for (boss_entity, mut boss_health) in bosses.iter_mut() {
-                if entity == boss_entity {
-                    boss_health.0 -= 1;
-                    if boss_health.0 == 0 {
-                        commands.entity(boss_entity).despawn_recursive();
-                    }
+            // Damage enemies and bosses
+            if damageable.contains(entity) {
+                if let Some(player) = game.player {
+                    hit_target(entity, player, bullet_struct.hit, &mut damage_event_writer, &mut status_event_writer);
                 }
             }
         }
     }
 }
 
-fn change_weapon(
+fn select_weapon(
     mut mouse_wheel_er: EventReader<bevy::input::mouse::MouseWheel>,
-    mut player_weapon: Query<&mut Weapon, With<Player>>,
+    actions: Res<Input<Action>>,
+    mut player_weapon: Query<(&mut Weapon, &mut Ammo, &mut Inventory, &mut Trigger), With<Player>>,
 ) {
+    use bevy::input::mouse::MouseScrollUnit;
+    // Trackpads report pixels, this many make one weapon step
+    const PIXELS_PER_STEP: f32 = 50.0;
+
+    let (mut weapon, mut ammo, mut inventory, mut trigger) = player_weapon.single_mut();
+    let mut selected = weapon.0;
+
+    // Slot actions pick weapon directly
+    for (slot, action) in Action::WEAPON_SLOTS.iter().enumerate() {
+        if actions.just_pressed(*action) && inventory.has(slot) {
+            selected = slot;
+        }
+    }
+
+    // Accumulate wheel movement, scrolling down goes to next weapon, with zoom modifier held it zooms camera instead
+    let zooming = actions.pressed(Action::ZoomModifier);
     for event in mouse_wheel_er.iter() {
-        if event.y == -1.0 {
-            player_weapon.single_mut().0 = match player_weapon.single_mut().0 {
-                WeaponType::Pistol => WeaponType::Shotgun, 
-                WeaponType::Shotgun => WeaponType::Rifle, 
-                WeaponType::Rifle => WeaponType::Pistol, 
-            }
+        if zooming {
+            continue;
         }
+        inventory.scroll += match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_STEP,
+        };
     }
-}
\ No newline at end of file
+    let steps = inventory.scroll.trunc();
+    if steps != 0.0 {
+        inventory.scroll -= steps;
+        selected = inventory.cycle(selected, -steps as i32);
+    }
+
+    if actions.just_pressed(Action::NextWeapon) {
+        selected = inventory.cycle(selected, 1);
+    }
+    if actions.just_pressed(Action::PreviousWeapon) {
+        selected = inventory.cycle(selected, -1);
+    }
+
+    if selected != weapon.0 && selected < ammo.weapons.len() {
+        weapon.0 = selected;
+
+        // Switching weapon interrupts reload and resets trigger
+        ammo.reload_timer = None;
+        *trigger = Trigger::default();
+    }
+}
@@ -0,0 +1,313 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::combat::Shield;
+use crate::GameState;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PowerUpKind {
+    DoubleDamage,
+    RapidFire,
+    SpeedBoost,
+    Shield,
+    MultiShot,
+}
+
+// What happens when power-up that is already active gets picked up again
+enum Stacking {
+    // Start full duration again
+    Refresh,
+    // Add duration on top of what is left, up to a limit
+    Extend { max_duration: f32 },
+    // Stronger effect with every stack, duration starts again
+    Stack { max_stacks: u32 },
+}
+
+pub trait PowerUpEffect: Send + Sync + 'static {
+    const KIND: PowerUpKind;
+}
+
+pub struct DoubleDamage;
+pub struct RapidFire;
+pub struct SpeedBoost;
+pub struct Overshield;
+pub struct MultiShot;
+
+impl PowerUpEffect for DoubleDamage {
+    const KIND: PowerUpKind = PowerUpKind::DoubleDamage;
+}
+impl PowerUpEffect for RapidFire {
+    const KIND: PowerUpKind = PowerUpKind::RapidFire;
+}
+impl PowerUpEffect for SpeedBoost {
+    const KIND: PowerUpKind = PowerUpKind::SpeedBoost;
+}
+impl PowerUpEffect for Overshield {
+    const KIND: PowerUpKind = PowerUpKind::Shield;
+}
+impl PowerUpEffect for MultiShot {
+    const KIND: PowerUpKind = PowerUpKind::MultiShot;
+}
+
+// Active power-up on the player, removed when it runs out
+#[derive(Component)]
+pub struct PowerUp<T: PowerUpEffect> {
+    pub remaining: f32,
+    pub stacks: u32,
+    effect: PhantomData<T>,
+}
+
+#[derive(Event)]
+pub struct PowerUpEvent {
+    pub target: Entity,
+    pub kind: PowerUpKind,
+}
+
+#[derive(Component)]
+struct PowerUpIcon(PowerUpKind);
+
+#[derive(Component)]
+struct PowerUpCountdown(PowerUpKind);
+
+// Hits absorbed by shield power-up
+const SHIELD_HITS: i32 = 3;
+
+const POWER_UP_KINDS: [PowerUpKind; 5] = [
+    PowerUpKind::DoubleDamage,
+    PowerUpKind::RapidFire,
+    PowerUpKind::SpeedBoost,
+    PowerUpKind::Shield,
+    PowerUpKind::MultiShot,
+];
+
+pub struct PowerUpsPlugin;
+impl Plugin for PowerUpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PowerUpEvent>()
+            .add_systems(Startup, setup_power_up_icons);
+        add_power_up::<DoubleDamage>(app);
+        add_power_up::<RapidFire>(app);
+        add_power_up::<SpeedBoost>(app);
+        add_power_up::<Overshield>(app);
+        add_power_up::<MultiShot>(app);
+    }
+}
+
+fn add_power_up<T: PowerUpEffect>(app: &mut App) {
+    app.add_systems(
+        Update,
+        (grant_power_up::<T>, tick_power_up::<T>, show_power_up::<T>).in_set(GameState::Playing),
+    );
+}
+
+impl PowerUpKind {
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUpKind::DoubleDamage => Color::CRIMSON,
+            PowerUpKind::RapidFire => Color::ORANGE,
+            PowerUpKind::SpeedBoost => Color::YELLOW,
+            PowerUpKind::Shield => Color::rgb(0.3, 0.6, 1.0),
+            PowerUpKind::MultiShot => Color::LIME_GREEN,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUpKind::DoubleDamage => "Double Damage",
+            PowerUpKind::RapidFire => "Rapid Fire",
+            PowerUpKind::SpeedBoost => "Speed",
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::MultiShot => "Multi-Shot",
+        }
+    }
+
+    // Short label shown on HUD icon
+    fn label(&self) -> &'static str {
+        match self {
+            PowerUpKind::DoubleDamage => "2x",
+            PowerUpKind::RapidFire => "RF",
+            PowerUpKind::SpeedBoost => "SP",
+            PowerUpKind::Shield => "SH",
+            PowerUpKind::MultiShot => "MS",
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        match self {
+            PowerUpKind::DoubleDamage => 10.0,
+            PowerUpKind::RapidFire => 8.0,
+            PowerUpKind::SpeedBoost => 8.0,
+            PowerUpKind::Shield => 15.0,
+            PowerUpKind::MultiShot => 10.0,
+        }
+    }
+
+    fn stacking(&self) -> Stacking {
+        match self {
+            PowerUpKind::DoubleDamage | PowerUpKind::Shield => Stacking::Refresh,
+            PowerUpKind::RapidFire => Stacking::Extend { max_duration: 20.0 },
+            PowerUpKind::SpeedBoost => Stacking::Stack { max_stacks: 2 },
+            PowerUpKind::MultiShot => Stacking::Stack { max_stacks: 3 },
+        }
+    }
+}
+
+impl<T: PowerUpEffect> PowerUp<T> {
+    fn new() -> Self {
+        PowerUp {
+            remaining: T::KIND.duration(),
+            stacks: 1,
+            effect: PhantomData,
+        }
+    }
+
+    fn stack(&mut self) {
+        let duration = T::KIND.duration();
+        match T::KIND.stacking() {
+            Stacking::Refresh => self.remaining = duration,
+            Stacking::Extend { max_duration } => {
+                self.remaining = (self.remaining + duration).min(max_duration);
+            }
+            Stacking::Stack { max_stacks } => {
+                self.stacks = (self.stacks + 1).min(max_stacks);
+                self.remaining = duration;
+            }
+        }
+    }
+}
+
+fn grant_power_up<T: PowerUpEffect>(
+    mut targets: Query<Option<&mut PowerUp<T>>>,
+    mut power_up_event_reader: EventReader<PowerUpEvent>,
+    mut commands: Commands,
+) {
+    for event in power_up_event_reader.iter() {
+        if event.kind != T::KIND {
+            continue;
+        }
+        let Ok(power_up) = targets.get_mut(event.target) else {
+            continue;
+        };
+        match power_up {
+            Some(mut power_up) => power_up.stack(),
+            None => {
+                commands.entity(event.target).insert(PowerUp::<T>::new());
+            }
+        }
+        if T::KIND == PowerUpKind::Shield {
+            commands.entity(event.target).insert(Shield(SHIELD_HITS));
+        }
+    }
+}
+
+fn tick_power_up<T: PowerUpEffect>(
+    mut power_ups: Query<(Entity, &mut PowerUp<T>)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut power_up) in power_ups.iter_mut() {
+        power_up.remaining -= time.delta_seconds();
+        if power_up.remaining <= 0.0 {
+            commands.entity(entity).remove::<PowerUp<T>>();
+            if T::KIND == PowerUpKind::Shield {
+                commands.entity(entity).remove::<Shield>();
+            }
+        }
+    }
+}
+
+// Row of icons in the top right corner, hidden until power-up is active
+fn setup_power_up_icons(asset_server: Res<AssetServer>, mut commands: Commands) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                right: Val::Px(5.0),
+                column_gap: Val::Px(5.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|row| {
+            for kind in POWER_UP_KINDS {
+                row.spawn(NodeBundle {
+                    style: Style {
+                        display: Display::None,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(PowerUpIcon(kind))
+                .with_children(|icon| {
+                    icon.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(40.0),
+                            height: Val::Px(40.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: kind.color().into(),
+                        ..default()
+                    })
+                    .with_children(|square| {
+                        square.spawn(TextBundle::from_section(
+                            kind.label(),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 22.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    });
+                    icon.spawn(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    ))
+                    .insert(PowerUpCountdown(kind));
+                });
+            }
+        });
+}
+
+fn show_power_up<T: PowerUpEffect>(
+    power_ups: Query<&PowerUp<T>>,
+    mut icons: Query<(&PowerUpIcon, &mut Style)>,
+    mut countdowns: Query<(&PowerUpCountdown, &mut Text)>,
+) {
+    // Only player gets power-ups
+    let power_up = power_ups.get_single().ok();
+
+    for (icon, mut style) in icons.iter_mut() {
+        if icon.0 != T::KIND {
+            continue;
+        }
+        let display = if power_up.is_some() { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+
+    let Some(power_up) = power_up else {
+        return;
+    };
+    for (countdown, mut text) in countdowns.iter_mut() {
+        if countdown.0 != T::KIND {
+            continue;
+        }
+        text.sections[0].value = if power_up.stacks > 1 {
+            format!("{:.0}s x{}", power_up.remaining.ceil(), power_up.stacks)
+        } else {
+            format!("{:.0}s", power_up.remaining.ceil())
+        };
+    }
+}
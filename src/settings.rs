@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::actions::{Action, Binding, Bindings, PAUSE_FALLBACK};
+use crate::gamepad::GamepadControls;
+use crate::{Difficulty, GameState};
+
+// Player preferences, effects can be turned off for accessibility
+#[derive(Resource)]
+pub struct Settings {
+    pub screen_shake: bool,
+    pub hit_stop: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            screen_shake: true,
+            hit_stop: true,
+        }
+    }
+}
+
+// Row of the settings menu, every action gets one to rebind it
+#[derive(Clone, Copy)]
+enum Row {
+    Difficulty,
+    ScreenShake,
+    HitStop,
+    Action(Action),
+}
+
+#[derive(Resource, Default)]
+struct SettingsMenu {
+    selected: usize,
+    // Binding of selected action being edited, one past the last adds a new binding
+    slot: usize,
+    // Next key or button pressed becomes binding in selected slot
+    waiting: bool,
+}
+
+#[derive(Component)]
+struct SettingsPanel;
+
+#[derive(Component)]
+struct SettingsText;
+
+const HIGHLIGHT_COLOR: Color = Color::rgb(0.9, 0.8, 0.2);
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // Menu runs while game is paused, so it stays out of GameState::Playing
+        app.init_resource::<Settings>()
+            .init_resource::<SettingsMenu>()
+            .add_systems(Startup, setup_settings_panel)
+            .add_systems(
+                Update,
+                (
+                    toggle_pause,
+                    navigate_settings.after(toggle_pause),
+                    show_settings.after(navigate_settings),
+                ),
+            );
+    }
+}
+
+fn rows() -> Vec<Row> {
+    [Row::Difficulty, Row::ScreenShake, Row::HitStop]
+        .into_iter()
+        .chain(Action::ALL.map(Row::Action))
+        .collect()
+}
+
+fn setup_settings_panel(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.0),
+                right: Val::Px(5.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(SettingsPanel)
+        .with_children(|panel| {
+            panel
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(SettingsText);
+        });
+}
+
+// Pausing stops gameplay systems and physics and opens the settings menu
+fn toggle_pause(
+    actions: Res<Input<Action>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut rapier_configuration: ResMut<RapierConfiguration>,
+    mut panel: Query<&mut Visibility, With<SettingsPanel>>,
+) {
+    // Pause binding itself can be rebound too
+    if menu.waiting || !actions.just_pressed(Action::Pause) {
+        return;
+    }
+    let pause = *state.get() == GameState::Playing;
+    next_state.set(if pause { GameState::Paused } else { GameState::Playing });
+    rapier_configuration.physics_pipeline_active = !pause;
+    *panel.single_mut() = if pause { Visibility::Inherited } else { Visibility::Hidden };
+    menu.selected = 0;
+    menu.slot = 0;
+}
+
+// Up/Down pick a row, Left/Right pick a binding, Enter toggles a setting or waits for new binding, Delete restores default bindings
+fn navigate_settings(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepad: Res<GamepadControls>,
+    state: Res<State<GameState>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+    mut difficulty: ResMut<Difficulty>,
+    mut bindings: ResMut<Bindings>,
+) {
+    if *state.get() != GameState::Paused {
+        return;
+    }
+    let rows = rows();
+
+    if menu.waiting {
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            menu.waiting = false;
+            return;
+        }
+        // Escape closes the game, so it can't be bound
+        let binding = keyboard_input
+            .get_just_pressed()
+            .find(|&&key| key != KeyCode::Escape)
+            .map(|&key| Binding::Key(key))
+            .or_else(|| mouse.get_just_pressed().next().map(|&button| Binding::Mouse(button)))
+            .or_else(|| gamepad.buttons.get_just_pressed().next().map(|&button| Binding::Gamepad(button)));
+        // Refused binding keeps waiting for another one
+        if let (Some(binding), Row::Action(action)) = (binding, rows[menu.selected]) {
+            if bindings.bind(action, menu.slot, binding) {
+                bindings.save();
+                menu.waiting = false;
+            }
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % rows.len();
+        menu.slot = 0;
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + rows.len() - 1) % rows.len();
+        menu.slot = 0;
+    }
+    if let Row::Action(action) = rows[menu.selected] {
+        let slots = bindings.get(action).len() + 1;
+        if keyboard_input.just_pressed(KeyCode::Right) {
+            menu.slot = (menu.slot + 1) % slots;
+        }
+        if keyboard_input.just_pressed(KeyCode::Left) {
+            menu.slot = (menu.slot + slots - 1) % slots;
+        }
+        // Bindings may have been reset to fewer slots
+        if menu.slot >= slots {
+            menu.slot = slots - 1;
+        }
+    }
+
+    let confirm = keyboard_input.just_pressed(KeyCode::Return);
+    match rows[menu.selected] {
+        Row::Difficulty if confirm => *difficulty = difficulty.next(),
+        Row::ScreenShake if confirm => settings.screen_shake = !settings.screen_shake,
+        Row::HitStop if confirm => settings.hit_stop = !settings.hit_stop,
+        Row::Action(_) if confirm => menu.waiting = true,
+        Row::Action(action) if keyboard_input.just_pressed(KeyCode::Delete) => {
+            bindings.reset(action);
+            bindings.save();
+            menu.slot = 0;
+        }
+        _ => {}
+    }
+}
+
+fn show_settings(
+    settings: Res<Settings>,
+    difficulty: Res<Difficulty>,
+    bindings: Res<Bindings>,
+    menu: Res<SettingsMenu>,
+    mut text: Query<&mut Text, With<SettingsText>>,
+) {
+    if !settings.is_changed() && !difficulty.is_changed() && !bindings.is_changed() && !menu.is_changed() {
+        return;
+    }
+    let on_off = |enabled: bool| if enabled { "On" } else { "Off" };
+    let mut text = text.single_mut();
+    let style = TextStyle {
+        color: Color::WHITE,
+        ..text.sections[0].style.clone()
+    };
+
+    let mut sections = vec![TextSection::new(
+        "Paused\n[Up/Down] select  [Left/Right] binding  [Enter] change  [Delete] default\n\n",
+        style.clone(),
+    )];
+    for (index, row) in rows().into_iter().enumerate() {
+        let selected = index == menu.selected;
+        let value = match row {
+            Row::Difficulty => format!("Difficulty: {}", difficulty.name()),
+            Row::ScreenShake => format!("Screen shake: {}", on_off(settings.screen_shake)),
+            Row::HitStop => format!("Hit-stop: {}", on_off(settings.hit_stop)),
+            Row::Action(action) if selected && menu.waiting => {
+                format!("{}: press a free key or button, [Backspace] cancels", action.name())
+            }
+            Row::Action(action) => {
+                let mut names: Vec<String> = bindings.get(action).iter().map(Binding::name).collect();
+                // Brackets mark the slot Enter rebinds, empty one adds a binding
+                if selected {
+                    names.push(String::new());
+                    names[menu.slot] = format!("[{}]", names[menu.slot]);
+                    names.retain(|name| !name.is_empty());
+                }
+                if action == Action::Pause {
+                    names.push(Binding::Key(PAUSE_FALLBACK).name());
+                }
+                format!("{}: {}", action.name(), names.join(", "))
+            }
+        };
+        let color = if selected { HIGHLIGHT_COLOR } else { Color::WHITE };
+        sections.push(TextSection::new(format!("{}\n", value), TextStyle { color, ..style.clone() }));
+    }
+    text.sections = sections;
+}
@@ -0,0 +1,266 @@
+use serde::Deserialize;
+use bevy::prelude::*;
+use bevy::render::mesh::shape as render_shape;
+
+use crate::combat::{despawn_dead, DamageEvent, DamageType};
+use crate::{FloatingTextEvent, GameState};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum StatusKind {
+    // Damage over time, stack up to make it hurt more
+    Burn,
+    Poison,
+    // Halves movement and attack speed
+    Slow,
+    // Can't move, turn or attack
+    Freeze,
+    // Can't attack or jump, staggers around at half speed
+    Stun,
+}
+
+// Status applied by weapon or attack
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: f32,
+}
+
+#[derive(Event)]
+pub struct StatusEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub effect: StatusEffect,
+}
+
+struct ActiveStatus {
+    kind: StatusKind,
+    remaining: f32,
+    stacks: u32,
+    tick_timer: Timer,
+    source: Option<Entity>,
+}
+
+// Statuses affecting entity, archetypes can be immune to some of them
+#[derive(Component)]
+pub struct StatusEffects {
+    active: Vec<ActiveStatus>,
+    immunities: Vec<StatusKind>,
+    // Marker above the head in color of the latest status
+    indicator: Option<(Entity, StatusKind)>,
+}
+
+pub struct StatusPlugin;
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StatusEvent>().add_systems(
+            Update,
+            (
+                apply_status_events,
+                tick_status_effects,
+                // Indicator is spawned as child, so owner must still be alive
+                show_status_indicators.before(despawn_dead),
+            )
+            .in_set(GameState::Playing),
+        );
+    }
+}
+
+impl StatusKind {
+    // Damage every tick for each stack, kept well below a direct hit so a few stacks don't kill outright
+    fn tick(&self) -> Option<(f32, i32, DamageType)> {
+        match self {
+            StatusKind::Burn => Some((0.5, 3, DamageType::Fire)),
+            StatusKind::Poison => Some((1.0, 2, DamageType::Kinetic)),
+            _ => None,
+        }
+    }
+
+    fn max_stacks(&self) -> u32 {
+        match self {
+            StatusKind::Burn => 3,
+            StatusKind::Poison => 3,
+            _ => 1,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Burn => "Burning",
+            StatusKind::Poison => "Poisoned",
+            StatusKind::Slow => "Slowed",
+            StatusKind::Freeze => "Frozen",
+            StatusKind::Stun => "Stunned",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            StatusKind::Burn => Color::ORANGE_RED,
+            StatusKind::Poison => Color::GREEN,
+            StatusKind::Slow => Color::rgb(0.5, 0.7, 1.0),
+            StatusKind::Freeze => Color::rgb(0.8, 0.95, 1.0),
+            StatusKind::Stun => Color::YELLOW,
+        }
+    }
+}
+
+impl StatusEffects {
+    pub fn new(immunities: &[StatusKind]) -> Self {
+        StatusEffects {
+            active: Vec::new(),
+            immunities: immunities.to_vec(),
+            indicator: None,
+        }
+    }
+
+    fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|status| status.kind == kind)
+    }
+
+    // Frozen and stunned entities can't attack or jump
+    pub fn can_act(&self) -> bool {
+        !self.has(StatusKind::Freeze) && !self.has(StatusKind::Stun)
+    }
+
+    pub fn can_move(&self) -> bool {
+        !self.has(StatusKind::Freeze)
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        if !self.can_move() {
+            0.0
+        } else if self.has(StatusKind::Slow) || self.has(StatusKind::Stun) {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    pub fn attack_speed_multiplier(&self) -> f32 {
+        if self.has(StatusKind::Slow) {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    // Returns false if entity is immune
+    fn apply(&mut self, effect: StatusEffect, source: Option<Entity>) -> bool {
+        if self.immunities.contains(&effect.kind) {
+            return false;
+        }
+
+        // Same status again adds stack and refreshes duration
+        match self.active.iter_mut().find(|status| status.kind == effect.kind) {
+            Some(status) => {
+                status.stacks = (status.stacks + 1).min(effect.kind.max_stacks());
+                status.remaining = status.remaining.max(effect.duration);
+                status.source = source;
+            }
+            None => {
+                let tick_time = effect.kind.tick().map_or(1.0, |tick| tick.0);
+                self.active.push(ActiveStatus {
+                    kind: effect.kind,
+                    remaining: effect.duration,
+                    stacks: 1,
+                    tick_timer: Timer::from_seconds(tick_time, TimerMode::Repeating),
+                    source,
+                });
+            }
+        }
+        true
+    }
+}
+
+fn apply_status_events(
+    mut targets: Query<(&mut StatusEffects, &Transform)>,
+    mut status_event_reader: EventReader<StatusEvent>,
+    mut floating_text_event_writer: EventWriter<FloatingTextEvent>,
+) {
+    for event in status_event_reader.iter() {
+        let Ok((mut status_effects, transform)) = targets.get_mut(event.target) else {
+            continue;
+        };
+        let newly_applied = !status_effects.has(event.effect.kind);
+        if !status_effects.apply(event.effect, event.source) {
+            floating_text_event_writer.send(FloatingTextEvent {
+                translation: transform.translation,
+                text: "Immune".into(),
+                color: Color::GRAY,
+                size: 30.0,
+            });
+        } else if newly_applied {
+            floating_text_event_writer.send(FloatingTextEvent {
+                translation: transform.translation,
+                text: event.effect.kind.name().into(),
+                color: event.effect.kind.color(),
+                size: 30.0,
+            });
+        }
+    }
+}
+
+fn tick_status_effects(
+    mut targets: Query<(Entity, &mut StatusEffects)>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut status_effects) in targets.iter_mut() {
+        for status in status_effects.active.iter_mut() {
+            status.remaining -= time.delta_seconds();
+            let Some((_, damage, damage_type)) = status.kind.tick() else {
+                continue;
+            };
+            if status.tick_timer.tick(time.delta()).just_finished() {
+                damage_event_writer.send(DamageEvent {
+                    target: entity,
+                    source: status.source,
+                    amount: damage * status.stacks as i32,
+                    damage_type,
+                    crit_chance: 0.0,
+                    over_time: true,
+                });
+            }
+        }
+        status_effects.active.retain(|status| status.remaining > 0.0);
+    }
+}
+
+fn show_status_indicators(
+    mut targets: Query<(Entity, &mut StatusEffects)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for (entity, mut status_effects) in targets.iter_mut() {
+        let latest = status_effects.active.last().map(|status| status.kind);
+        if latest == status_effects.indicator.map(|indicator| indicator.1) {
+            continue;
+        }
+
+        if let Some((indicator, _)) = status_effects.indicator.take() {
+            commands.entity(indicator).despawn_recursive();
+        }
+        let Some(kind) = latest else {
+            continue;
+        };
+        let sphere = render_shape::UVSphere {
+            radius: 0.12,
+            ..default()
+        };
+        let indicator = commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(Mesh::from(sphere)),
+                material: materials.add(StandardMaterial {
+                    base_color: kind.color(),
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_xyz(0.0, 1.4, 0.0),
+                ..default()
+            })
+            .id();
+        commands.entity(entity).add_child(indicator);
+        status_effects.indicator = Some((indicator, kind));
+    }
+}
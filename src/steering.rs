@@ -0,0 +1,92 @@
+use rand::Rng;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+// Crowd steering parameters, tuned per enemy archetype
+#[derive(Component, Clone)]
+pub struct Steering {
+    pub separation_radius: f32,
+    pub separation_weight: f32,
+    pub avoidance_distance: f32,
+    pub avoidance_weight: f32,
+    pub surround_radius: f32,
+    pub flank_angle: f32,
+}
+
+impl Steering {
+    // Every agent approaches target from its own angle, so crowd spreads around it
+    pub fn random_flank(max_flank_angle: f32) -> f32 {
+        rand::thread_rng().gen_range(-max_flank_angle..=max_flank_angle)
+    }
+
+    // Point next to target offset by agent's flank angle, target itself once agent gets close
+    pub fn approach_point(&self, position: Vec3, target: Vec3) -> Vec3 {
+        let offset = Vec3::new(position.x - target.x, 0.0, position.z - target.z);
+        if offset.length() < self.surround_radius + 0.5 {
+            return target;
+        }
+        target + Quat::from_rotation_y(self.flank_angle) * offset.normalize() * self.surround_radius
+    }
+
+    // Combine direction agent wants to go with separation from neighbours and avoidance of obstacles
+    pub fn steer(
+        &self,
+        entity: Entity,
+        position: Vec3,
+        desired_direction: Vec3,
+        neighbours: &[(Entity, Vec3)],
+        rapier_context: &RapierContext,
+    ) -> Vec3 {
+        let separation = self.separation(entity, position, neighbours);
+        let avoidance = self.avoidance(position, desired_direction, rapier_context);
+
+        let steered = desired_direction
+            + separation * self.separation_weight
+            + avoidance * self.avoidance_weight;
+        Vec3::new(steered.x, 0.0, steered.z).normalize_or_zero()
+    }
+
+    fn separation(&self, entity: Entity, position: Vec3, neighbours: &[(Entity, Vec3)]) -> Vec3 {
+        let mut push = Vec3::ZERO;
+        for (neighbour_entity, neighbour_position) in neighbours {
+            if *neighbour_entity == entity {
+                continue;
+            }
+            let away = Vec3::new(
+                position.x - neighbour_position.x,
+                0.0,
+                position.z - neighbour_position.z,
+            );
+            let distance = away.length();
+            if distance > 0.0 && distance < self.separation_radius {
+                // Push harder the closer neighbour is
+                push += away / distance * (1.0 - distance / self.separation_radius);
+            }
+        }
+        push
+    }
+
+    fn avoidance(&self, position: Vec3, direction: Vec3, rapier_context: &RapierContext) -> Vec3 {
+        if direction == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+
+        // Feel ahead with three rays and steer away from level geometry they hit
+        let mut push = Vec3::ZERO;
+        for angle in [-0.5, 0.0, 0.5] {
+            let ray_dir = Quat::from_rotation_y(angle) * direction;
+            let filter = QueryFilter::only_fixed();
+            if let Some((_entity, intersection)) = rapier_context.cast_ray_and_get_normal(
+                position,
+                ray_dir,
+                self.avoidance_distance,
+                true,
+                filter,
+            ) {
+                let strength = 1.0 - intersection.toi / self.avoidance_distance;
+                push += Vec3::new(intersection.normal.x, 0.0, intersection.normal.z) * strength;
+            }
+        }
+        push
+    }
+}
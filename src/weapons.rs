@@ -0,0 +1,425 @@
+use serde::Deserialize;
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::render::mesh::shape as render_shape;
+use bevy::utils::BoxedFuture;
+use bevy_rapier3d::prelude::*;
+
+use crate::player::Player;
+use crate::combat::{DamageEvent, DamageType};
+use crate::status::{StatusEffect, StatusEvent};
+use crate::feedback::ExplosionEvent;
+use crate::{GameState, Health};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum FireMode {
+    // One shot per click
+    Semi,
+    // Keeps firing while held
+    Auto,
+    // Fixed number of shots per click
+    Burst { shots: u32, interval: f32 },
+    // Fires on release once charged
+    Charge { charge_time: f32 },
+}
+
+// What leaves the barrel and how it deals damage
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Projectile {
+    // Straight flying ball hitting first thing on the way
+    #[default]
+    Bullet,
+    // Arcs under gravity and damages everything around where it lands
+    Grenade { blast_radius: f32 },
+    // Hits everything in short cone with every shot, so fast firing flames should leave damage to status
+    Flame { cone_angle: f32 },
+    // Instant ray going through every target up to first wall
+    Rail,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub fire_mode: FireMode,
+    #[serde(default)]
+    pub projectile: Projectile,
+    // Shots per second
+    pub fire_rate: f32,
+    pub pellets: u32,
+    // Whole angle pellets are fanned over, in radians
+    pub spread: f32,
+    pub projectile_speed: f32,
+    pub range: f32,
+    #[serde(default)]
+    pub damage: i32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub crit_chance: f32,
+    // Status applied to everything weapon hits
+    #[serde(default)]
+    pub status: Option<StatusEffect>,
+    // Extra random spread added by every shot, wears off over time
+    pub recoil: f32,
+    pub magazine_size: u32,
+    // None means unlimited spare ammo
+    pub max_reserve: Option<u32>,
+    pub reload_time: f32,
+}
+
+#[derive(Deserialize, TypeUuid, TypePath)]
+#[uuid = "8f4f5d2e-7c3a-4b8e-9a61-2f0c5e7d9b13"]
+pub struct WeaponsAsset {
+    pub weapons: Vec<WeaponDefinition>,
+}
+
+#[derive(Default)]
+struct WeaponsLoader;
+
+// Everything that happens to target hit by weapon, carried by projectiles until they land
+#[derive(Clone, Copy)]
+pub struct Hit {
+    pub damage: i32,
+    pub damage_type: DamageType,
+    pub crit_chance: f32,
+    pub status: Option<StatusEffect>,
+}
+
+#[derive(Component)]
+struct Grenade {
+    source: Entity,
+    blast_radius: f32,
+    hit: Hit,
+    fuse: Timer,
+}
+
+// Pull on grenades, same as rapier default
+const GRAVITY: f32 = 9.81;
+
+// Short lived visual like flame puff, rail beam or explosion
+#[derive(Component)]
+struct Effect {
+    velocity: Vec3,
+    time_to_live: f32,
+}
+
+#[derive(Resource)]
+pub struct Arsenal {
+    handle: Handle<WeaponsAsset>,
+}
+
+pub struct WeaponsPlugin;
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<WeaponsAsset>()
+            .init_asset_loader::<WeaponsLoader>()
+            .add_systems(Startup, load_weapons)
+            .add_systems(
+                Update,
+                (move_grenades, update_effects).in_set(GameState::Playing),
+            );
+    }
+}
+
+impl AssetLoader for WeaponsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let weapons = ron::de::from_bytes::<WeaponsAsset>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(weapons));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapons.ron"]
+    }
+}
+
+impl WeaponDefinition {
+    pub fn hit(&self) -> Hit {
+        Hit {
+            damage: self.damage,
+            damage_type: self.damage_type,
+            crit_chance: self.crit_chance,
+            status: self.status,
+        }
+    }
+}
+
+impl Arsenal {
+    // Definitions are available once the asset finishes loading
+    pub fn definitions<'a>(&self, assets: &'a Assets<WeaponsAsset>) -> Option<&'a [WeaponDefinition]> {
+        assets.get(&self.handle).map(|asset| asset.weapons.as_slice())
+    }
+}
+
+fn load_weapons(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(Arsenal {
+        handle: asset_server.load("data/arsenal.weapons.ron"),
+    });
+}
+
+// Damage target and apply weapon status to it
+pub fn hit_target(
+    target: Entity,
+    source: Entity,
+    hit: Hit,
+    damage_event_writer: &mut EventWriter<DamageEvent>,
+    status_event_writer: &mut EventWriter<StatusEvent>,
+) {
+    if hit.damage > 0 {
+        damage_event_writer.send(DamageEvent {
+            target,
+            source: Some(source),
+            amount: hit.damage,
+            damage_type: hit.damage_type,
+            crit_chance: hit.crit_chance,
+            over_time: false,
+        });
+    }
+    if let Some(effect) = hit.status {
+        status_event_writer.send(StatusEvent {
+            target,
+            source: Some(source),
+            effect,
+        });
+    }
+}
+
+fn spawn_effect(
+    transform: Transform,
+    velocity: Vec3,
+    time_to_live: f32,
+    mesh: Mesh,
+    color: Color,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    commands: &mut Commands,
+) {
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color: color,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            transform,
+            ..default()
+        })
+        .insert(Effect {
+            velocity,
+            time_to_live,
+        });
+}
+
+// Launch grenade so it lands `distance` away, move_grenades pulls it down into an arc
+pub fn fire_grenade(
+    origin: Vec3,
+    direction: Vec3,
+    distance: f32,
+    blast_radius: f32,
+    definition: &WeaponDefinition,
+    source: Entity,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    commands: &mut Commands,
+) {
+    let flight_time = distance.min(definition.range) / definition.projectile_speed;
+    let velocity = direction * definition.projectile_speed + Vec3::Y * GRAVITY * flight_time / 2.0;
+
+    let sphere = render_shape::UVSphere {
+        radius: 0.15,
+        ..default()
+    };
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(Mesh::from(sphere)),
+            material: materials.add(Color::DARK_GREEN.into()),
+            transform: Transform::from_translation(origin),
+            ..default()
+        })
+        .insert(Grenade {
+            source,
+            blast_radius,
+            hit: definition.hit(),
+            fuse: Timer::from_seconds(4.0, TimerMode::Once),
+        })
+        // Without collider it has no mass and rapier gravity wouldn't touch it, so it falls on its own
+        .insert(RigidBody::KinematicVelocityBased)
+        .insert(Velocity::linear(velocity));
+}
+
+// Hit every target inside the cone that isn't behind a wall
+pub fn fire_flame(
+    origin: Vec3,
+    direction: Vec3,
+    cone_angle: f32,
+    definition: &WeaponDefinition,
+    source: Entity,
+    targets: &Query<(Entity, &Transform), (With<Health>, Without<Player>)>,
+    rapier_context: &RapierContext,
+    damage_event_writer: &mut EventWriter<DamageEvent>,
+    status_event_writer: &mut EventWriter<StatusEvent>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    commands: &mut Commands,
+) {
+    let hit = definition.hit();
+    for (entity, transform) in targets.iter() {
+        let offset = transform.translation - origin;
+        let flat_offset = Vec3::new(offset.x, 0.0, offset.z);
+        let distance = flat_offset.length();
+        if distance > definition.range || distance == 0.0 || flat_offset.angle_between(direction) > cone_angle / 2.0 {
+            continue;
+        }
+        let blocked = rapier_context
+            .cast_ray(origin, flat_offset / distance, distance, true, QueryFilter::only_fixed())
+            .is_some();
+        if blocked {
+            continue;
+        }
+
+        hit_target(entity, source, hit, damage_event_writer, status_event_writer);
+    }
+
+    let puff_direction = Quat::from_rotation_y(rand::random::<f32>() * cone_angle - cone_angle / 2.0) * direction;
+    let sphere = render_shape::UVSphere {
+        radius: 0.2,
+        ..default()
+    };
+    spawn_effect(
+        Transform::from_translation(origin),
+        puff_direction * definition.projectile_speed,
+        definition.range / definition.projectile_speed,
+        Mesh::from(sphere),
+        Color::ORANGE_RED,
+        meshes,
+        materials,
+        commands,
+    );
+}
+
+// Damage every target on the line until the ray reaches a wall
+pub fn fire_rail(
+    origin: Vec3,
+    direction: Vec3,
+    definition: &WeaponDefinition,
+    source: Entity,
+    targets: &Query<(Entity, &Transform), (With<Health>, Without<Player>)>,
+    rapier_context: &RapierContext,
+    damage_event_writer: &mut EventWriter<DamageEvent>,
+    status_event_writer: &mut EventWriter<StatusEvent>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    commands: &mut Commands,
+) {
+    let length = rapier_context
+        .cast_ray(origin, direction, definition.range, true, QueryFilter::only_fixed())
+        .map_or(definition.range, |(_entity, toi)| toi);
+
+    let filter = QueryFilter::default().exclude_collider(source);
+    rapier_context.intersections_with_ray(origin, direction, length, true, filter, |entity, _intersection| {
+        if targets.contains(entity) {
+            hit_target(entity, source, definition.hit(), damage_event_writer, status_event_writer);
+        }
+        true
+    });
+
+    // Thin beam stretched between origin and end of the ray
+    spawn_effect(
+        Transform::from_translation(origin + direction * length / 2.0).looking_to(direction, Vec3::Y),
+        Vec3::ZERO,
+        0.15,
+        Mesh::from(render_shape::Box::new(0.05, 0.05, length)),
+        Color::CYAN,
+        meshes,
+        materials,
+        commands,
+    );
+}
+
+fn move_grenades(
+    mut grenades: Query<(Entity, &Transform, &mut Velocity, &mut Grenade)>,
+    targets: Query<(), (With<Health>, Without<Player>)>,
+    rapier_context: Res<RapierContext>,
+    mut damage_event_writer: EventWriter<DamageEvent>,
+    mut status_event_writer: EventWriter<StatusEvent>,
+    mut explosion_event_writer: EventWriter<ExplosionEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (grenade_entity, transform, mut velocity, mut grenade) in grenades.iter_mut() {
+        velocity.linvel.y -= GRAVITY * time.delta_seconds();
+
+        // Explode on first contact along this frame's movement or when fuse runs out
+        let shape = Collider::ball(0.15);
+        let filter = QueryFilter::default().exclude_collider(grenade.source);
+        let contact = rapier_context.cast_shape(
+            transform.translation,
+            transform.rotation,
+            velocity.linvel,
+            &shape,
+            time.delta_seconds(),
+            filter,
+        );
+        if contact.is_none() && !grenade.fuse.tick(time.delta()).finished() {
+            continue;
+        }
+        let translation = match contact {
+            Some((_, toi)) => transform.translation + velocity.linvel * toi.toi,
+            None => transform.translation,
+        };
+
+        commands.entity(grenade_entity).despawn_recursive();
+        explosion_event_writer.send(ExplosionEvent {
+            translation,
+            radius: grenade.blast_radius,
+        });
+
+        let blast = Collider::ball(grenade.blast_radius);
+        rapier_context.intersections_with_shape(translation, Quat::IDENTITY, &blast, QueryFilter::default(), |entity| {
+            if targets.contains(entity) {
+                hit_target(entity, grenade.source, grenade.hit, &mut damage_event_writer, &mut status_event_writer);
+            }
+            true
+        });
+
+        let sphere = render_shape::UVSphere {
+            radius: grenade.blast_radius,
+            ..default()
+        };
+        spawn_effect(
+            Transform::from_translation(translation),
+            Vec3::ZERO,
+            0.2,
+            Mesh::from(sphere),
+            Color::rgba(1.0, 0.5, 0.0, 0.5),
+            &mut meshes,
+            &mut materials,
+            &mut commands,
+        );
+    }
+}
+
+fn update_effects(
+    mut effects: Query<(Entity, &mut Transform, &mut Effect)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut effect) in effects.iter_mut() {
+        transform.translation += effect.velocity * time.delta_seconds();
+        effect.time_to_live -= time.delta_seconds();
+        if effect.time_to_live <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}